@@ -0,0 +1,5 @@
+import random
+import sys
+
+random.seed(int(sys.argv[1]))
+print(random.randint(1, 2**31 - 1))
@@ -0,0 +1,2 @@
+n = int(input())
+print("true" if n % 2 == 0 else "false")
@@ -0,0 +1,36 @@
+import sys
+
+MAX_GUESSES = 30
+
+
+def reject(reason):
+    print(reason, file=sys.stderr)
+    sys.exit(1)
+
+
+def main():
+    with open(sys.argv[1]) as f:
+        n = int(f.read())
+    with open(sys.argv[2]) as f:
+        secret = int(f.read())
+    print(n, flush=True)
+
+    for _ in range(MAX_GUESSES):
+        line = sys.stdin.readline()
+        if not line:
+            reject("The solution stopped before guessing the number")
+        try:
+            guess = int(line)
+        except ValueError:
+            reject(f"Expected a number, got {line.strip()!r}")
+        if guess < secret:
+            print("higher", flush=True)
+        elif guess > secret:
+            print("lower", flush=True)
+        else:
+            print("correct", flush=True)
+            return
+    reject(f"No guesses left after {MAX_GUESSES}")
+
+
+main()
@@ -3,16 +3,27 @@ use std::path::PathBuf;
 use std::process::exit;
 use std::time::Duration;
 
-use crate::PROBLEMS;
+use crate::init::Config;
+use crate::list::{ListFormat, ListOptions, SortBy};
+use crate::report::ReportFormat;
+use crate::stress::StressOptions;
+use crate::test_runner::TestOptions;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::CommandFactory;
 use clap::{arg, command, Args, Parser, Subcommand};
-use clap::{value_parser, CommandFactory};
-use macro_types::Problem;
+use macro_types::{Difficulty, Problem};
 
-type ProblemId = u64;
+/// Id or slug of a problem, e.g. `2` or `odd-even-check`
+type ProblemId = String;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct _Cli {
+    /// Load problems from this directory instead of the built-in ones
+    #[arg(long, global = true, env = "CHINT_PROBLEMS_DIR", value_name = "DIR")]
+    problems_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     command: CliCommand,
 }
@@ -20,35 +31,183 @@ struct _Cli {
 #[derive(Subcommand)]
 enum CliCommand {
     /// List all the available problems
-    /// Example:
-    /// 	chint list
+    /// Examples:
+    ///     chint list
+    ///     chint list --tag math --difficulty easy --unsolved
     #[clap(verbatim_doc_comment)]
-    List,
+    List(ListCommand),
 
+    #[command(after_help = EXIT_CODES_HELP)]
     Test(TestCommand),
 
     /// Show description of a problem
     /// Examples:
-    /// 	chint show 1
-    /// 	chint show 10
+    ///     chint show 1
+    ///     chint show odd-even-check
     #[clap(verbatim_doc_comment)]
     Show(ShowCommand),
 
+    /// Create a directory with a solution skeleton and the tests of a problem,
+    /// `chint test` in it doesn't need any arguments
+    /// Examples:
+    ///     chint init odd-even-check --lang python
+    ///     chint init 2 --lang cpp --dir solutions/parity
+    #[clap(verbatim_doc_comment)]
+    Init(InitCommand),
+
+    /// Look for an input on which a solution differs from a reference solution
+    /// Examples:
+    ///     chint stress 2 --gen gen.py --ref brute.py --sol sol.py
+    ///     chint stress odd-even-check --gen gen.py --ref brute.py --sol sol.cpp -n 10000
+    #[clap(verbatim_doc_comment)]
+    #[command(after_help = EXIT_CODES_HELP)]
+    Stress(StressCommand),
+
+    /// Maintain the problems in --problems-dir
+    #[command(subcommand)]
+    Problem(ProblemCommand),
+
+    /// Summary of your progress: solved problems, solve rates and streaks
+    Stats,
+
     /// Generate shell completions
     /// Examples:
-    /// 	chint completion bash
-    /// 	chint completion fish
-    Completion {
-        shell: clap_complete::Shell,
-    },
+    ///     chint completion bash
+    ///     chint completion fish
+    Completion { shell: clap_complete::Shell },
+}
+
+#[derive(Args)]
+struct ListCommand {
+    /// Only problems with this tag, can be repeated
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    #[arg(long, value_parser = parse_difficulty())]
+    difficulty: Option<Difficulty>,
+
+    /// Only problems with this text in the title, description or tags
+    #[arg(long, value_name = "TEXT")]
+    search: Option<String>,
+
+    /// Hide the problems you have already solved
+    #[arg(long)]
+    unsolved: bool,
+
+    #[arg(long, value_enum, default_value_t = SortBy::Id)]
+    sort: SortBy,
+
+    #[arg(long, value_enum, default_value_t = ListFormat::Text)]
+    format: ListFormat,
+}
+
+#[derive(Args)]
+struct InitCommand {
+    /// Id or slug of the problem
+    #[arg(value_name = "PROBLEM")]
+    problem_id: ProblemId,
+
+    /// e.g. python, js, ruby, c, cpp, rust, go, java or one from ~/.config/chint/languages.toml
+    #[arg(short, long)]
+    lang: String,
+
+    /// Directory to create [default: slug of the problem]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct StressCommand {
+    /// Id or slug of the problem
+    #[arg(value_name = "PROBLEM")]
+    problem_id: ProblemId,
+
+    /// Prints a random input, run with the seed as its only argument
+    /// [default: the problem's generator]
+    #[arg(long = "gen", value_name = "FILE")]
+    generator: Option<PathBuf>,
+
+    /// A solution known to be correct, e.g. a slow brute force. Its outputs are the expected
+    /// outputs [default: the problem's reference solution]
+    #[arg(long = "ref", value_name = "FILE")]
+    reference: Option<PathBuf>,
+
+    /// The solution to test
+    #[arg(long = "sol", value_name = "FILE")]
+    solution: PathBuf,
+
+    /// Number of inputs to try
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    iterations: u64,
+
+    /// Seed of the first input, the next ones count up from it
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Seconds the solution can run, overrides the problem's time limits
+    /// [default: problem's time limit or 60]
+    #[arg(short, long, value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+
+    /// Seconds the generator and the reference solution can run
+    #[arg(long, default_value = "60", value_name = "SECONDS", value_parser = parse_seconds)]
+    ref_timeout: Duration,
+
+    /// Multiply the time limits by this, e.g. for slower languages like Python
+    #[arg(short = 'x', long, default_value_t = 1.0, value_name = "FACTOR", value_parser = parse_factor)]
+    time_multiplier: f64,
+
+    /// Megabytes of memory the solution can use, overrides the problem's memory limit
+    #[arg(short, long, value_name = "MB")]
+    memory_limit: Option<u64>,
+
+    /// Directory to save the failing input and expected output in
+    #[arg(long, default_value = ".", value_name = "DIR")]
+    save_dir: PathBuf,
+
+    /// Run the solution in the sandbox, see `chint test --sandbox`
+    #[arg(long)]
+    sandbox: bool,
+}
+
+#[derive(Subcommand)]
+enum ProblemCommand {
+    /// Write the outputs of the reference solution to the `.out` files of the tests
+    /// Examples:
+    ///     chint --problems-dir ./problems problem regen 2
+    ///     chint --problems-dir ./problems problem regen
+    #[clap(verbatim_doc_comment)]
+    Regen(RegenCommand),
+}
+
+#[derive(Args)]
+struct RegenCommand {
+    /// Ids or slugs of the problems [default: all the problems with a reference solution]
+    #[arg(value_name = "PROBLEM")]
+    problem_ids: Vec<ProblemId>,
 }
 
 #[derive(Args)]
 struct ShowCommand {
-    #[arg(value_parser = value_parser!(u64).range(1..PROBLEMS.len() as u64+1))]
-    problem_id: u64,
+    /// Id or slug of the problem
+    #[arg(value_name = "PROBLEM")]
+    problem_id: ProblemId,
 }
 
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  All tests passed
+  1  Internal error, e.g. the solution couldn't be started
+  2  Invalid arguments
+  3  Wrong answer
+  4  Runtime error, the solution crashed or exited with a non-zero exit code
+  5  Time limit exceeded
+  6  Memory limit exceeded
+  7  Compilation error
+  8  Security violation, the sandbox stopped the solution
+  9  Output limit exceeded
+The first failed test decides the exit code.";
+
 const TEST_HELP: &'static str = r#"
 Usage:
 Test your solution for a problem
@@ -66,19 +225,68 @@ Examples:
 #[derive(Args)]
 #[clap()]
 struct TestCommand {
-    #[arg(value_parser = value_parser!(u64).range(1..))]
-    problem_id: u64,
+    /// Id or slug of the problem [default: the one in chint.toml, see `chint init`]
+    #[arg(value_name = "PROBLEM")]
+    problem_id: Option<ProblemId>,
+
+    /// Seconds to wait for solution to complete, overrides the problem's time limits
+    /// [default: problem's time limit or 60]
+    #[arg(short, long, value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+
+    /// Multiply the time limits by this, e.g. for slower languages like Python
+    #[arg(short = 'x', long, default_value_t = 1.0, value_name = "FACTOR", value_parser = parse_factor)]
+    time_multiplier: f64,
+
+    /// Apply the timeout to CPU time (user + system) like online judges do, instead of
+    /// wall-clock time. The wall-clock time is still limited to twice the timeout
+    #[arg(long)]
+    cpu_time: bool,
+
+    /// Megabytes of memory the solution can use, overrides the problem's memory limit
+    #[arg(short, long, value_name = "MB")]
+    memory_limit: Option<u64>,
+
+    /// Megabytes the solution can write to stdout
+    #[arg(long, default_value_t = 64, value_name = "MB")]
+    output_limit: u64,
 
-    /// Seconds to wait for solution to complete
-    #[arg(short, long, default_value_t = 60)]
-    timeout: u64,
+    /// Run all the tests instead of stopping at the first failure and print a summary at the end
+    #[arg(short, long, visible_alias = "keep-going")]
+    all: bool,
+
+    /// Number of tests to run at the same time, results are still printed in order
+    #[arg(short, long, default_value_t = 1, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: u16,
+
+    /// Pin each parallel test to its own CPU, for more accurate timing with --jobs. Linux only
+    #[arg(long)]
+    pin_cpus: bool,
+
+    /// Run the solution isolated from the system, without network and with a read-only
+    /// filesystem, e.g. for grading untrusted solutions. Linux only
+    #[arg(long)]
+    sandbox: bool,
+
+    /// Also write the results to a file, e.g. for CI
+    #[arg(long, value_name = "FORMAT")]
+    report: Option<ReportFormat>,
+
+    /// File to write the report to [default: chint-report.json or chint-report.xml]
+    #[arg(long, value_name = "PATH", requires = "report")]
+    report_file: Option<PathBuf>,
+
+    /// Run the tests again whenever the solution file changes
+    #[arg(short, long)]
+    watch: bool,
 
     #[command(flatten)]
     sol: _SolutionSpec,
 }
 
+/// Defaults to the solution in chint.toml
 #[derive(Args)]
-#[group(required = true, multiple = false)]
+#[group(multiple = false)]
 struct _SolutionSpec {
     file: Option<PathBuf>,
     #[arg(short, long)]
@@ -86,51 +294,233 @@ struct _SolutionSpec {
 }
 
 pub enum Command {
-    List,
+    List {
+        options: ListOptions,
+    },
     Show {
         problem: &'static Problem<'static>,
     },
+    Init {
+        problem: &'static Problem<'static>,
+        language: String,
+        dir: PathBuf,
+    },
+    Stats,
+    Stress {
+        problem: &'static Problem<'static>,
+        generator: Option<PathBuf>,
+        reference: Option<PathBuf>,
+        solution: PathBuf,
+        options: StressOptions,
+    },
+    Regen {
+        problems: Vec<&'static Problem<'static>>,
+        /// The `--problems-dir` the problems are in
+        dir: PathBuf,
+        options: TestOptions,
+    },
     Test {
         problem: &'static Problem<'static>,
         spec: SolutionSpec,
-        timeout: Duration,
+        options: TestOptions,
+        report: Option<(ReportFormat, PathBuf)>,
+    },
+    /// Like `Test`, but again whenever the solution changes
+    Watch {
+        problem: &'static Problem<'static>,
+        file: PathBuf,
+        options: TestOptions,
+        report: Option<(ReportFormat, PathBuf)>,
     },
 }
-fn get_problem(id: ProblemId) -> &'static Problem<'static> {
-    &PROBLEMS[id as usize - 1]
+fn get_problem(id: &str) -> &'static Problem<'static> {
+    let problems = crate::problems();
+    let problem = match id.parse::<u64>() {
+        Ok(id) => problems.iter().find(|p| p.id == id),
+        Err(_) => problems.iter().find(|p| p.slug == id),
+    };
+    match problem {
+        Some(problem) => problem,
+        None => _Cli::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!(
+                    "No problem with id or slug {id:?}, there are {} problems",
+                    problems.len()
+                ),
+            )
+            .exit(),
+    }
 }
 
-impl From<_Cli> for Command {
-    fn from(value: _Cli) -> Self {
-        match value.command {
-            CliCommand::List => Self::List,
+impl TryFrom<_Cli> for Command {
+    type Error = anyhow::Error;
+
+    fn try_from(value: _Cli) -> anyhow::Result<Self> {
+        Ok(match value.command {
+            CliCommand::List(list) => Self::List {
+                options: ListOptions {
+                    tags: list.tags,
+                    difficulty: list.difficulty,
+                    search: list.search,
+                    unsolved: list.unsolved,
+                    sort: list.sort,
+                    format: list.format,
+                },
+            },
             CliCommand::Show(show) => Self::Show {
-                problem: get_problem(show.problem_id),
+                problem: get_problem(&show.problem_id),
             },
+            CliCommand::Init(init) => {
+                let problem = get_problem(&init.problem_id);
+                Self::Init {
+                    problem,
+                    language: init.lang,
+                    dir: init
+                        .dir
+                        .unwrap_or_else(|| PathBuf::from(problem.slug.as_ref())),
+                }
+            }
             CliCommand::Test(test) => {
-                if let Some(command) = test.sol.command {
-                    Self::Test {
-                        problem: get_problem(test.problem_id),
-                        spec: SolutionSpec::Command(command),
-                        timeout: Duration::from_secs(test.timeout),
-                    }
-                } else if let Some(file) = test.sol.file {
-                    Self::Test {
-                        problem: get_problem(test.problem_id),
-                        spec: SolutionSpec::File(file),
-                        timeout: Duration::from_secs(test.timeout),
-                    }
-                } else {
-                    unreachable!()
+                let options = TestOptions {
+                    timeout: test.timeout,
+                    time_multiplier: test.time_multiplier,
+                    cpu_time: test.cpu_time,
+                    memory_limit: test.memory_limit,
+                    output_limit: test.output_limit,
+                    keep_going: test.all,
+                    jobs: test.jobs.into(),
+                    pin_cpus: test.pin_cpus,
+                    sandbox: test.sandbox,
+                    ..TestOptions::default()
+                };
+                let report = test.report.map(|format| {
+                    let path = test.report_file.unwrap_or_else(|| format.default_path());
+                    (format, path)
+                });
+                // Only needed if something is missing from the arguments
+                let config = match (&test.problem_id, &test.sol.command, &test.sol.file) {
+                    (Some(_), Some(_), _) | (Some(_), _, Some(_)) => None,
+                    _ => Config::find()?,
+                };
+                let problem_id = match (test.problem_id, &config) {
+                    (Some(id), _) => id,
+                    (None, Some((config, _))) => config.problem.clone(),
+                    (None, None) => _Cli::command()
+                        .error(
+                            ErrorKind::MissingRequiredArgument,
+                            "PROBLEM is required outside of a `chint init` directory",
+                        )
+                        .exit(),
+                };
+                let spec = match (test.sol.command, test.sol.file, config) {
+                    (Some(command), _, _) => SolutionSpec::Command(command),
+                    (None, Some(file), _) => SolutionSpec::File(file),
+                    (None, None, Some((config, dir))) => SolutionSpec::File(dir.join(config.solution)),
+                    (None, None, None) => _Cli::command()
+                        .error(
+                            ErrorKind::MissingRequiredArgument,
+                            "A solution file or --command is required outside of a `chint init` directory",
+                        )
+                        .exit(),
+                };
+                let problem = get_problem(&problem_id);
+                match (test.watch, spec) {
+                    (false, spec) => Self::Test {
+                        problem,
+                        spec,
+                        options,
+                        report,
+                    },
+                    (true, SolutionSpec::File(file)) => Self::Watch {
+                        problem,
+                        file,
+                        options,
+                        report,
+                    },
+                    (true, SolutionSpec::Command(_)) => _Cli::command()
+                        .error(
+                            ErrorKind::ArgumentConflict,
+                            "--watch needs a solution file instead of --command",
+                        )
+                        .exit(),
                 }
             }
+            CliCommand::Stress(stress) => Self::Stress {
+                problem: get_problem(&stress.problem_id),
+                generator: stress.generator,
+                reference: stress.reference,
+                solution: stress.solution,
+                options: StressOptions {
+                    iterations: stress.iterations,
+                    seed: stress.seed,
+                    test: TestOptions {
+                        timeout: stress.timeout,
+                        time_multiplier: stress.time_multiplier,
+                        memory_limit: stress.memory_limit,
+                        sandbox: stress.sandbox,
+                        ..TestOptions::default()
+                    },
+                    reference_timeout: stress.ref_timeout,
+                    save_dir: stress.save_dir,
+                },
+            },
+            CliCommand::Problem(ProblemCommand::Regen(regen)) => {
+                let Some(dir) = value.problems_dir else {
+                    _Cli::command()
+                        .error(
+                            ErrorKind::MissingRequiredArgument,
+                            "--problems-dir is required, the built-in problems can't be changed",
+                        )
+                        .exit()
+                };
+                let problems = match regen.problem_ids.is_empty() {
+                    true => crate::problems()
+                        .iter()
+                        .filter(|p| p.reference.is_some())
+                        .collect(),
+                    false => regen.problem_ids.iter().map(|id| get_problem(id)).collect(),
+                };
+                Self::Regen {
+                    problems,
+                    dir,
+                    options: TestOptions::default(),
+                }
+            }
+            CliCommand::Stats => Self::Stats,
             CliCommand::Completion { shell } => {
                 let mut cli = _Cli::command();
                 let name = cli.get_name().to_string();
                 clap_complete::generate(shell, &mut cli, name, &mut io::stdout());
                 exit(0);
             }
+        })
+    }
+}
+
+fn parse_difficulty() -> impl TypedValueParser<Value = Difficulty> {
+    PossibleValuesParser::new(["easy", "medium", "hard"]).map(|s| match s.as_str() {
+        "easy" => Difficulty::Easy,
+        "medium" => Difficulty::Medium,
+        _ => Difficulty::Hard,
+    })
+}
+
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    match arg.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => {
+            Duration::try_from_secs_f64(seconds).map_err(|e| format!("{e}"))
         }
+        Ok(_) => Err("must be a positive number".to_string()),
+        Err(e) => Err(format!("{e}")),
+    }
+}
+
+fn parse_factor(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(factor),
+        Ok(_) => Err("must be a positive number".to_string()),
+        Err(e) => Err(format!("{e}")),
     }
 }
 
@@ -139,8 +529,12 @@ pub enum SolutionSpec {
     File(PathBuf),
 }
 
-pub fn get_args() -> Command {
-    return _Cli::parse().into();
+pub fn get_args() -> anyhow::Result<Command> {
+    let cli = _Cli::parse();
+    if let Some(dir) = &cli.problems_dir {
+        crate::load_problems(dir)?;
+    }
+    cli.try_into()
 }
 
 #[test]
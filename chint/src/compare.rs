@@ -0,0 +1,179 @@
+use std::fmt::{self, Display, Formatter};
+
+use macro_types::CompareMode;
+use serde::Serialize;
+
+/// First place where an output differs from the expected output
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Mismatch {
+    /// 1-based line of the expected output, or of the received output if it is too long
+    pub line: usize,
+    /// `None` if the received output is too long
+    pub expected: Option<String>,
+    /// `None` if the received output ended too early
+    pub received: Option<String>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let show = |token: &Option<String>| match token {
+            Some(token) => format!("{:?}", token),
+            None => "<end of output>".to_string(),
+        };
+        write!(
+            f,
+            "line {}: expected {}, received {}",
+            self.line,
+            show(&self.expected),
+            show(&self.received)
+        )
+    }
+}
+
+/// Returns `None` if `received` matches `expected` under `mode`
+pub fn compare(mode: &CompareMode, received: &str, expected: &str) -> Option<Mismatch> {
+    match mode {
+        CompareMode::Exact => compare_exact(received, expected),
+        CompareMode::Lines => compare_items(lines(received), lines(expected), |a, b| a == b),
+        CompareMode::Tokens => compare_items(tokens(received), tokens(expected), |a, b| a == b),
+        CompareMode::Float { absolute, relative } => {
+            compare_items(tokens(received), tokens(expected), |a, b| {
+                floats_equal(a, b, *absolute, *relative)
+            })
+        }
+        CompareMode::CaseInsensitive => {
+            compare_items(tokens(received), tokens(expected), |a, b| {
+                a.eq_ignore_ascii_case(b)
+            })
+        }
+    }
+}
+
+fn compare_exact(received: &str, expected: &str) -> Option<Mismatch> {
+    if received == expected {
+        return None;
+    }
+    // Both strings are the same up to `at`, so it is a char boundary in both
+    let at = received
+        .char_indices()
+        .zip(expected.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(received.len().min(expected.len()), |((i, _), _)| i);
+    let line_start = expected[..at].rfind('\n').map_or(0, |i| i + 1);
+    let line_of = |text: &str| {
+        text[line_start..]
+            .split_inclusive('\n')
+            .next()
+            .map(str::to_string)
+    };
+
+    Some(Mismatch {
+        line: expected[..at].matches('\n').count() + 1,
+        expected: line_of(expected),
+        received: line_of(received),
+    })
+}
+
+/// Lines with their 1-based line number, without trailing whitespace and trailing empty lines
+fn lines(text: &str) -> Vec<(usize, &str)> {
+    let mut lines: Vec<_> = text
+        .lines()
+        .map(str::trim_end)
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .collect();
+    while lines.last().is_some_and(|(_, line)| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Whitespace separated tokens with their 1-based line number
+fn tokens(text: &str) -> Vec<(usize, &str)> {
+    text.lines()
+        .enumerate()
+        .flat_map(|(i, line)| line.split_whitespace().map(move |token| (i + 1, token)))
+        .collect()
+}
+
+fn compare_items(
+    received: Vec<(usize, &str)>,
+    expected: Vec<(usize, &str)>,
+    equal: impl Fn(&str, &str) -> bool,
+) -> Option<Mismatch> {
+    let mut received = received.into_iter();
+    let mut expected = expected.into_iter();
+    loop {
+        match (received.next(), expected.next()) {
+            (None, None) => return None,
+            (Some((_, r)), Some((_, e))) if equal(r, e) => continue,
+            (r, e) => {
+                return Some(Mismatch {
+                    line: e.or(r).map(|(line, _)| line).unwrap(),
+                    expected: e.map(|(_, e)| e.to_string()),
+                    received: r.map(|(_, r)| r.to_string()),
+                })
+            }
+        }
+    }
+}
+
+fn floats_equal(received: &str, expected: &str, absolute: f64, relative: f64) -> bool {
+    match (received.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(r), Ok(e)) => {
+            let difference = (r - e).abs();
+            r == e || difference <= absolute || difference <= relative * e.abs()
+        }
+        _ => received == expected,
+    }
+}
+
+#[test]
+fn compare_modes() {
+    let float = CompareMode::Float {
+        absolute: 1e-6,
+        relative: 0.0,
+    };
+
+    assert_eq!(compare(&CompareMode::Exact, "1 2\n", "1 2\n"), None);
+    assert!(compare(&CompareMode::Exact, "1 2\n", "1 2").is_some());
+    assert_eq!(compare(&CompareMode::Lines, "1 2  \n\n", "1 2\n"), None);
+    assert!(compare(&CompareMode::Lines, "1  2\n", "1 2\n").is_some());
+    assert_eq!(compare(&CompareMode::Tokens, "1  2\n", "1\n2\n"), None);
+    assert_eq!(compare(&float, "0.3333334", "0.333333"), None);
+    assert!(compare(&float, "0.3334", "0.333333").is_some());
+    assert_eq!(compare(&CompareMode::CaseInsensitive, "YES", "yes"), None);
+
+    assert_eq!(
+        compare(&CompareMode::Tokens, "1 2\n3 5\n", "1 2\n3 4\n"),
+        Some(Mismatch {
+            line: 2,
+            expected: Some("4".into()),
+            received: Some("5".into()),
+        })
+    );
+    assert_eq!(
+        compare(&CompareMode::Tokens, "1", "1 2"),
+        Some(Mismatch {
+            line: 1,
+            expected: Some("2".into()),
+            received: None,
+        })
+    );
+    assert_eq!(
+        compare(&CompareMode::Exact, "ab\ncd", "ab\nce"),
+        Some(Mismatch {
+            line: 2,
+            expected: Some("ce".into()),
+            received: Some("cd".into()),
+        })
+    );
+    assert_eq!(
+        compare(&CompareMode::Exact, "1 2", "1 2\n"),
+        Some(Mismatch {
+            line: 1,
+            expected: Some("1 2\n".into()),
+            received: Some("1 2".into()),
+        })
+    );
+}
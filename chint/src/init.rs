@@ -0,0 +1,122 @@
+//! `chint init`, a working directory for solving a problem
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use macro_types::Problem;
+use serde::{Deserialize, Serialize};
+
+use crate::language::{self, Language};
+
+/// Name of the config file in a working directory
+pub const CONFIG_FILE: &str = "chint.toml";
+
+fn builtin_template(file_name: &str) -> Option<&'static str> {
+    Some(match file_name {
+        "solution.py" => include_str!("../templates/solution.py"),
+        "solution.js" => include_str!("../templates/solution.js"),
+        "solution.rb" => include_str!("../templates/solution.rb"),
+        "solution.c" => include_str!("../templates/solution.c"),
+        "solution.cpp" => include_str!("../templates/solution.cpp"),
+        "solution.rs" => include_str!("../templates/solution.rs"),
+        "solution.go" => include_str!("../templates/solution.go"),
+        "solution.java" => include_str!("../templates/solution.java"),
+        _ => return None,
+    })
+}
+
+/// The language's template from `$XDG_CONFIG_HOME/chint/templates` if it is there, or the
+/// built-in one
+fn template(language: &Language) -> anyhow::Result<String> {
+    let file_name = language
+        .template
+        .as_deref()
+        .with_context(|| format!("No template for {}", language.name))?;
+    if let Some(dir) = dirs::config_dir() {
+        let path = dir.join("chint").join("templates").join(file_name);
+        if path.exists() {
+            return fs::read_to_string(&path)
+                .with_context(|| format!("Unable to read {}", path.display()));
+        }
+    }
+    match builtin_template(file_name) {
+        Some(template) => Ok(template.to_string()),
+        None => bail!("Template {file_name:?} of {} not found", language.name),
+    }
+}
+
+/// Contents of [`CONFIG_FILE`]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Slug of the problem
+    pub problem: String,
+    /// Name of the language, see [`language::by_name`]
+    pub language: String,
+    /// Relative to the config file
+    pub solution: PathBuf,
+}
+
+impl Config {
+    /// Look for [`CONFIG_FILE`] in the current directory and its parents, returns the
+    /// config and the directory it is in
+    pub fn find() -> anyhow::Result<Option<(Config, PathBuf)>> {
+        let cwd = std::env::current_dir().context("Unable to get the current directory")?;
+        for dir in cwd.ancestors() {
+            let path = dir.join(CONFIG_FILE);
+            if !path.exists() {
+                continue;
+            }
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Unable to read {}", path.display()))?;
+            let config = toml::from_str(&content)
+                .with_context(|| format!("Invalid config {}", path.display()))?;
+            return Ok(Some((config, dir.to_path_buf())));
+        }
+        Ok(None)
+    }
+}
+
+/// Create `dir` with a solution skeleton, the tests of the problem and a [`Config`]
+pub fn init(problem: &Problem, language: &str, dir: &Path) -> anyhow::Result<()> {
+    let language =
+        language::by_name(language)?.with_context(|| format!("Unknown language {language:?}"))?;
+    let solution = language.solution_file();
+    for file in [solution.as_path(), Path::new(CONFIG_FILE)] {
+        if dir.join(file).exists() {
+            bail!("{} already exists", dir.join(file).display());
+        }
+    }
+
+    let template = template(&language)?
+        .replace("{{title}}", &problem.title)
+        .replace("{{slug}}", &problem.slug);
+
+    let tests_dir = dir.join("tests");
+    fs::create_dir_all(&tests_dir)
+        .with_context(|| format!("Unable to create {}", tests_dir.display()))?;
+    for test in problem.tests.iter() {
+        for (extension, content) in [("in", &test.input), ("out", &test.output)] {
+            let path = tests_dir.join(format!("{}.{extension}", test.test_name));
+            fs::write(&path, content.as_ref())
+                .with_context(|| format!("Unable to write {}", path.display()))?;
+        }
+    }
+
+    let config = Config {
+        problem: problem.slug.to_string(),
+        language: language.name.clone(),
+        solution: solution.clone(),
+    };
+    for (file, content) in [
+        (solution.as_path(), template),
+        (Path::new(CONFIG_FILE), toml::to_string(&config)?),
+    ] {
+        let path = dir.join(file);
+        fs::write(&path, content).with_context(|| format!("Unable to write {}", path.display()))?;
+    }
+
+    println!("Created {}", dir.join(solution).display());
+    println!("Run `chint test` in {} to test it", dir.display());
+    Ok(())
+}
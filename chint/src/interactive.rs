@@ -0,0 +1,218 @@
+//! Interactive problems, the solution talks with the problem's interactor through pipes
+use std::io::{self, Read, Write};
+use std::process::{ChildStdin, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::process::{self, Limits, Termination, Usage};
+
+/// Bytes of the transcript that are kept, the rest is dropped
+const TRANSCRIPT_LIMIT: usize = 1024 * 1024;
+
+/// Starts the lines the solution wrote in the transcript
+const SOLUTION_PREFIX: &[u8] = b"> ";
+/// Starts the lines the interactor wrote in the transcript
+const INTERACTOR_PREFIX: &[u8] = b"< ";
+
+pub struct Interaction {
+    pub solution: (Termination, Usage),
+    pub solution_stderr: Vec<u8>,
+    pub interactor: (Termination, Usage),
+    pub interactor_stderr: Vec<u8>,
+    /// What the two programs said to each other, see [`Transcript`]
+    pub transcript: String,
+}
+
+/// Runs `solution` and `interactor` with the stdout of each one connected to the stdin of the
+/// other, both under their own limits. The output limit of the solution applies to what it
+/// writes to the interactor.
+pub fn run(
+    solution: &mut Command,
+    interactor: &mut Command,
+    solution_limits: &Limits,
+    interactor_limits: &Limits,
+) -> io::Result<Interaction> {
+    let (mut solution, _solution_running) = process::spawn(solution)?;
+    let (mut interactor, _interactor_running) = match process::spawn(interactor) {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            let _ = process::kill_group(&solution);
+            let _ = solution.wait();
+            return Err(e);
+        }
+    };
+    let solution_stdin = solution.stdin.take().expect("stdin should be piped");
+    let solution_stdout = solution.stdout.take().expect("stdout should be piped");
+    let solution_stderr = solution.stderr.take().expect("stderr should be piped");
+    let interactor_stdin = interactor.stdin.take().expect("stdin should be piped");
+    let interactor_stdout = interactor.stdout.take().expect("stdout should be piped");
+    let interactor_stderr = interactor.stderr.take().expect("stderr should be piped");
+
+    let solution_limit = solution_limits.output.unwrap_or(u64::MAX);
+    let interactor_limit = interactor_limits.output.unwrap_or(u64::MAX);
+    let transcript = Mutex::new(Transcript::default());
+    let output_exceeded = AtomicBool::new(false);
+    // The interactor is trusted, its output isn't limited
+    let never = AtomicBool::new(false);
+
+    let mut interaction = thread::scope(|scope| {
+        let to_interactor = scope.spawn(|| {
+            relay(
+                solution_stdout,
+                interactor_stdin,
+                SOLUTION_PREFIX,
+                &transcript,
+                solution_limit,
+                Some(&output_exceeded),
+            )
+        });
+        let to_solution = scope.spawn(|| {
+            relay(
+                interactor_stdout,
+                solution_stdin,
+                INTERACTOR_PREFIX,
+                &transcript,
+                u64::MAX,
+                None,
+            )
+        });
+        let solution_stderr =
+            scope.spawn(|| process::read_bounded(solution_stderr, solution_limit, None));
+        let interactor_stderr =
+            scope.spawn(|| process::read_bounded(interactor_stderr, interactor_limit, None));
+        let interactor_waited = scope.spawn(|| {
+            let waited = process::wait(&mut interactor, interactor_limits, &never);
+            if waited.is_err() {
+                let _ = process::kill_group(&interactor);
+            }
+            waited
+        });
+
+        let solution_waited = process::wait(&mut solution, solution_limits, &output_exceeded);
+        if solution_waited.is_err() {
+            // Otherwise the interactor and the threads might never finish
+            let _ = process::kill_group(&solution);
+        }
+        let interactor_waited = process::join(interactor_waited);
+        let solution_stderr = process::join(solution_stderr)?;
+        let interactor_stderr = process::join(interactor_stderr)?;
+        process::join(to_interactor)?;
+        process::join(to_solution)?;
+
+        let (termination, usage) = solution_waited?;
+        io::Result::Ok(Interaction {
+            solution: (
+                process::output_checked(termination, &output_exceeded),
+                usage,
+            ),
+            solution_stderr,
+            interactor: interactor_waited?,
+            interactor_stderr,
+            transcript: String::new(),
+        })
+    })?;
+    interaction.transcript = transcript
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_string();
+    Ok(interaction)
+}
+
+/// Copies `source` to `destination` and records it in `transcript`, sets `exceeded` after
+/// `limit` bytes. Closes `destination` at the end of `source`, so the other program sees it.
+fn relay(
+    mut source: impl Read,
+    destination: ChildStdin,
+    prefix: &'static [u8],
+    transcript: &Mutex<Transcript>,
+    limit: u64,
+    exceeded: Option<&AtomicBool>,
+) -> io::Result<()> {
+    let mut destination = Some(destination);
+    let mut relayed = 0;
+    let mut buffer = [0; 8192];
+    loop {
+        let read = match source.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        relayed += read as u64;
+        if relayed > limit {
+            if let Some(exceeded) = exceeded {
+                exceeded.store(true, Ordering::Relaxed);
+            }
+        }
+        transcript
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(prefix, &buffer[..read]);
+        if let Some(stdin) = &mut destination {
+            match stdin.write_all(&buffer[..read]).and_then(|_| stdin.flush()) {
+                // The other program exited, the rest is still read so that this one doesn't
+                // get stuck on a full pipe
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => destination = None,
+                result => result?,
+            }
+        }
+    }
+}
+
+/// Both sides of an interaction, each line starts with [`SOLUTION_PREFIX`] or
+/// [`INTERACTOR_PREFIX`] depending on who wrote it
+#[derive(Default)]
+struct Transcript {
+    text: Vec<u8>,
+    /// Prefix of the last line
+    prefix: Option<&'static [u8]>,
+    /// The last line ended with a newline
+    line_ended: bool,
+    truncated: bool,
+}
+
+impl Transcript {
+    fn record(&mut self, prefix: &'static [u8], data: &[u8]) {
+        for line in data.split_inclusive(|&b| b == b'\n') {
+            if self.text.len() >= TRANSCRIPT_LIMIT {
+                self.truncated = true;
+                return;
+            }
+            match self.prefix {
+                // Continues a line that was read in two parts
+                Some(last) if last == prefix && !self.line_ended => {}
+                Some(_) if !self.line_ended => {
+                    self.text.push(b'\n');
+                    self.text.extend_from_slice(prefix);
+                }
+                _ => self.text.extend_from_slice(prefix),
+            }
+            self.text.extend_from_slice(line);
+            self.prefix = Some(prefix);
+            self.line_ended = line.ends_with(b"\n");
+        }
+    }
+
+    fn into_string(self) -> String {
+        let mut text = String::from_utf8_lossy(&self.text).into_owned();
+        if self.truncated {
+            if !self.line_ended {
+                text.push('\n');
+            }
+            text.push_str("... (truncated)\n");
+        }
+        text
+    }
+}
+
+#[test]
+fn transcript() {
+    let mut transcript = Transcript::default();
+    transcript.record(INTERACTOR_PREFIX, b"10\n");
+    transcript.record(SOLUTION_PREFIX, b"5");
+    transcript.record(SOLUTION_PREFIX, b"\n");
+    transcript.record(INTERACTOR_PREFIX, b"higher\ncor");
+    transcript.record(SOLUTION_PREFIX, b"8");
+    assert_eq!(transcript.into_string(), "< 10\n> 5\n< higher\n< cor\n> 8");
+}
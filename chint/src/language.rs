@@ -0,0 +1,277 @@
+//! Languages that solutions (and checkers) can be written in, and how to run them
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+/// Languages that work without any configuration
+const BUILTIN: &str = include_str!("../languages.toml");
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Language {
+    pub name: String,
+    /// Files with these extensions are in this language, the first one is used for new files
+    pub extensions: Vec<String>,
+    /// Run once before the tests. `{source}` is replaced with the path of the source file and
+    /// `{output}` with a path in an empty directory for the compiled program
+    pub compile: Option<String>,
+    /// Placeholders are replaced like in `compile`
+    pub run: String,
+    /// File name of the skeleton for `chint init`
+    pub template: Option<String>,
+}
+
+/// The compiler rejected the source
+pub struct CompilationError {
+    pub command: String,
+    /// stdout and stderr of the compiler
+    pub output: String,
+}
+
+/// Contents of `languages.toml`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguagesFile {
+    #[serde(default)]
+    language: Vec<LanguageConfig>,
+}
+
+/// Everything but the name is optional, to override only some fields of a language
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguageConfig {
+    name: String,
+    extensions: Option<Vec<String>>,
+    compile: Option<String>,
+    run: Option<String>,
+    template: Option<String>,
+}
+
+/// The built-in languages with the ones in `$XDG_CONFIG_HOME/chint/languages.toml`
+pub fn all() -> anyhow::Result<Vec<Language>> {
+    let mut languages = merge(vec![], BUILTIN).context("Invalid built-in languages")?;
+    if let Some(dir) = dirs::config_dir() {
+        let path = dir.join("chint").join("languages.toml");
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Unable to read {}", path.display()))?;
+            languages = merge(languages, &content)
+                .with_context(|| format!("Invalid {}", path.display()))?;
+        }
+    }
+    Ok(languages)
+}
+
+/// Adds the languages in `config` to `languages`, replacing the fields of the ones with the
+/// same name
+fn merge(mut languages: Vec<Language>, config: &str) -> anyhow::Result<Vec<Language>> {
+    let file: LanguagesFile = toml::from_str(config)?;
+    for config in file.language {
+        match languages.iter_mut().find(|l| l.name == config.name) {
+            Some(language) => {
+                if let Some(extensions) = config.extensions {
+                    language.extensions = extensions;
+                }
+                if let Some(compile) = config.compile {
+                    language.compile = Some(compile);
+                }
+                if let Some(run) = config.run {
+                    language.run = run;
+                }
+                if let Some(template) = config.template {
+                    language.template = Some(template);
+                }
+            }
+            None => {
+                let (Some(extensions), Some(run)) = (config.extensions, config.run) else {
+                    bail!("Language {:?} needs `extensions` and `run`", config.name);
+                };
+                if extensions.is_empty() {
+                    bail!("Language {:?} needs at least one extension", config.name);
+                }
+                languages.push(Language {
+                    name: config.name,
+                    extensions,
+                    compile: config.compile,
+                    run,
+                    template: config.template,
+                });
+            }
+        }
+    }
+    Ok(languages)
+}
+
+pub fn by_name(name: &str) -> anyhow::Result<Option<Language>> {
+    Ok(all()?.into_iter().find(|l| l.name == name))
+}
+
+/// If several languages have the extension of `path`, the one defined last wins, so that
+/// user defined languages take precedence over the built-in ones
+pub fn by_file(path: &Path) -> anyhow::Result<Option<Language>> {
+    Ok(find_by_file(all()?, path))
+}
+
+fn find_by_file(languages: Vec<Language>, path: &Path) -> Option<Language> {
+    let extension = path.extension().and_then(|e| e.to_str())?;
+    languages
+        .into_iter()
+        .rev()
+        .find(|l| l.extensions.iter().any(|e| e == extension))
+}
+
+impl Language {
+    /// Name of new solution files, e.g. for `chint init`
+    pub fn solution_file(&self) -> PathBuf {
+        PathBuf::from(format!("solution.{}", self.extensions[0]))
+    }
+
+    /// Compiles `source` if the language needs it and returns the command to run it.
+    ///
+    /// Compiled programs are cached in [`build_dir`] by the hash of the source and the
+    /// compile command, so unchanged solutions are compiled only once.
+    pub fn prepare(&self, source: &Path) -> anyhow::Result<Result<String, CompilationError>> {
+        let compile = match &self.compile {
+            Some(compile) => compile,
+            None => return Ok(Ok(fill(&self.run, source, Path::new(""))?)),
+        };
+
+        let content =
+            fs::read(source).with_context(|| format!("Unable to read {}", source.display()))?;
+        let mut hasher = DefaultHasher::new();
+        (compile, content).hash(&mut hasher);
+        let root = open_build_dir()?;
+        let dir = root.join(format!("{:016x}", hasher.finish()));
+        let run = fill(&self.run, source, &dir.join("solution"))?;
+        // Written before the directory is moved into place
+        let done = dir.join(".done");
+        if done.exists() {
+            check_owner(&dir)?;
+            return Ok(Ok(run));
+        }
+
+        // Built next to the cache and moved there when done, another chint might be
+        // compiling the same source
+        let building = tempfile::Builder::new()
+            .prefix(".building-")
+            .tempdir_in(&root)
+            .with_context(|| format!("Unable to create a directory in {}", root.display()))?;
+        let output = building.path().join("solution");
+        let command = fill(compile, source, &output)?;
+        println!("=== Compiling: {command}");
+        let args = shlex::split(&command).context("Invalid compile command")?;
+        if args.is_empty() {
+            bail!("Empty compile command for {}", self.name);
+        }
+        let result = Command::new(&args[0])
+            .args(&args[1..])
+            .output()
+            .with_context(|| format!("Unable to run the compiler {:?}", args[0]))?;
+        if !result.status.success() {
+            let output = String::from_utf8_lossy(&result.stdout).to_string()
+                + &String::from_utf8_lossy(&result.stderr);
+            return Ok(Err(CompilationError { command, output }));
+        }
+        fs::write(building.path().join(".done"), "")?;
+        let building = building.into_path();
+        if let Err(e) = fs::rename(&building, &dir) {
+            let _ = fs::remove_dir_all(&building);
+            // Another chint finished first
+            if !done.exists() {
+                return Err(e).with_context(|| format!("Unable to create {}", dir.display()));
+            }
+        }
+        Ok(Ok(run))
+    }
+}
+
+/// Where compiled programs are cached, private to the user
+pub fn build_dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("chint").join("build"))
+}
+
+/// Creates [`build_dir`] if needed and checks that only the user can change it, the
+/// programs in it are run
+fn open_build_dir() -> anyhow::Result<PathBuf> {
+    let dir = build_dir().context("Unable to find the cache directory")?;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("Unable to create {}", dir.display()))?;
+    check_owner(&dir)?;
+    Ok(dir)
+}
+
+fn check_owner(path: &Path) -> anyhow::Result<()> {
+    let metadata =
+        fs::symlink_metadata(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    // SAFETY: geteuid has no preconditions
+    let uid = unsafe { libc::geteuid() };
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o022 != 0 {
+        bail!(
+            "{} must be a directory owned by you that others can't write to",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Replaces the placeholders in `template` with quoted paths
+fn fill(template: &str, source: &Path, output: &Path) -> anyhow::Result<String> {
+    let quote = |path: &Path| -> anyhow::Result<String> {
+        let path = path.to_str().context("Path is not valid UTF-8")?;
+        Ok(shlex::quote(path).to_string())
+    };
+    Ok(template
+        .replace("{source}", &quote(source)?)
+        .replace("{output}", &quote(output)?))
+}
+
+#[test]
+fn run_commands() {
+    // Not `by_file`, the user's own languages would be used
+    let builtin = merge(vec![], BUILTIN).unwrap();
+    let command = |file: &str| {
+        let path = Path::new(file);
+        let language = find_by_file(builtin.clone(), path);
+        language.map(|l| l.prepare(path).unwrap().ok().unwrap())
+    };
+
+    assert!(command("abc.xyz").is_none());
+    assert_eq!(command("abc.py"), Some("python3 abc.py".into()));
+    assert_eq!(command("hey/abc.py"), Some("python3 hey/abc.py".into()));
+    assert_eq!(command("hey/a b.js"), Some(r#"node "hey/a b.js""#.into()));
+}
+
+#[test]
+fn override_languages() {
+    let builtin = merge(vec![], BUILTIN).unwrap();
+    let config = r#"
+        [[language]]
+        name = "cpp"
+        compile = "clang++ -O2 -o {output} {source}"
+
+        [[language]]
+        name = "kotlin"
+        extensions = ["kt"]
+        compile = "kotlinc {source} -include-runtime -d {output}.jar"
+        run = "java -jar {output}.jar"
+    "#;
+
+    let languages = merge(builtin.clone(), config).unwrap();
+
+    let cpp = languages.iter().find(|l| l.name == "cpp").unwrap();
+    assert_eq!(
+        cpp.compile.as_deref(),
+        Some("clang++ -O2 -o {output} {source}")
+    );
+    assert_eq!(cpp.run, "{output}");
+    assert_eq!(languages.len(), builtin.len() + 1);
+    assert!(merge(builtin, "[[language]]\nname = \"zig\"").is_err());
+}
@@ -0,0 +1,139 @@
+//! `chint list`, filtering and sorting the problems
+use clap::ValueEnum;
+use macro_types::{Difficulty, Problem};
+use serde::Serialize;
+
+use crate::progress::{ProblemProgress, Progress};
+
+pub struct ListOptions {
+    /// Problems must have all of these
+    pub tags: Vec<String>,
+    pub difficulty: Option<Difficulty>,
+    /// Searched in the title, description and tags, ignoring case
+    pub search: Option<String>,
+    pub unsolved: bool,
+    pub sort: SortBy,
+    pub format: ListFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SortBy {
+    Id,
+    Title,
+    Difficulty,
+    /// Number of tests
+    Tests,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ListFormat {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    id: u64,
+    slug: &'a str,
+    title: &'a str,
+    difficulty: Option<Difficulty>,
+    tags: Vec<&'a str>,
+    author: Option<&'a str>,
+    source: Option<&'a str>,
+    tests: usize,
+    attempts: u64,
+    solved: bool,
+    /// In seconds, see [`ProblemProgress::best_time`]
+    best_time: Option<f64>,
+}
+
+impl ListOptions {
+    fn matches(&self, problem: &Problem, progress: Option<&ProblemProgress>) -> bool {
+        let has_tag = |tag: &String| problem.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        if !self.tags.iter().all(has_tag) {
+            return false;
+        }
+        if self.difficulty.is_some() && problem.difficulty != self.difficulty {
+            return false;
+        }
+        if self.unsolved && progress.is_some_and(|p| p.solved) {
+            return false;
+        }
+        match &self.search {
+            Some(search) => {
+                let search = search.to_lowercase();
+                problem.title.to_lowercase().contains(&search)
+                    || problem.description.to_lowercase().contains(&search)
+                    || problem
+                        .tags
+                        .iter()
+                        .any(|t| t.to_lowercase().contains(&search))
+            }
+            None => true,
+        }
+    }
+}
+
+pub fn list(options: &ListOptions) -> anyhow::Result<()> {
+    // Listing the problems is still useful without the progress
+    let progress = Progress::load().unwrap_or_else(|e| {
+        eprintln!("Unable to load progress: {e:#}");
+        Progress::default()
+    });
+    let mut problems: Vec<(&Problem, Option<ProblemProgress>)> = crate::problems()
+        .iter()
+        .map(|problem| (problem, progress.get(&problem.slug)))
+        .filter(|(problem, progress)| options.matches(problem, progress.as_ref()))
+        .collect();
+
+    match options.sort {
+        SortBy::Id => {}
+        SortBy::Title => problems.sort_by_key(|(p, _)| p.title.to_lowercase()),
+        // Problems without a difficulty go last
+        SortBy::Difficulty => problems.sort_by_key(|(p, _)| (p.difficulty.is_none(), p.difficulty)),
+        SortBy::Tests => problems.sort_by_key(|(p, _)| p.tests.len()),
+    }
+
+    match options.format {
+        ListFormat::Text => {
+            for (problem, progress) in problems {
+                let mark = match progress {
+                    Some(p) if p.solved => '✓',
+                    Some(_) => '~',
+                    None => ' ',
+                };
+                let mut line = format!("{mark} {} {}: {}", problem.id, problem.slug, problem.title);
+                if let Some(difficulty) = problem.difficulty {
+                    line += &format!(" [{difficulty}]");
+                }
+                if !problem.tags.is_empty() {
+                    line += &format!(" {{{}}}", problem.tags.join(", "));
+                }
+                println!("{line} (tests: {})", problem.tests.len());
+            }
+        }
+        ListFormat::Json => {
+            let entries: Vec<_> = problems
+                .into_iter()
+                .map(|(problem, progress)| {
+                    let progress = progress.unwrap_or_default();
+                    Entry {
+                        id: problem.id,
+                        slug: &problem.slug,
+                        title: &problem.title,
+                        difficulty: problem.difficulty,
+                        tags: problem.tags.iter().map(|t| t.as_ref()).collect(),
+                        author: problem.author.as_deref(),
+                        source: problem.source.as_deref(),
+                        tests: problem.tests.len(),
+                        attempts: progress.attempts,
+                        solved: progress.solved,
+                        best_time: progress.best_time.map(|t| t.as_secs_f64()),
+                    }
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+        }
+    }
+    Ok(())
+}
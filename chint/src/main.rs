@@ -1,68 +1,200 @@
 #[warn(clippy::unimplemented)]
 mod cli;
-mod command_guesser;
+mod compare;
+mod init;
+mod interactive;
+mod language;
+mod list;
+mod process;
+mod program;
+mod progress;
+mod regen;
+mod report;
+#[cfg(target_os = "linux")]
+mod sandbox;
+mod stats;
+mod stress;
 mod test_runner;
 mod text_diff;
+mod watch;
 
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::OnceLock;
 
 use anyhow::{bail, Context};
 use cli::{Command, SolutionSpec};
 use macro_types::Problem;
+use report::ReportFormat;
+use test_runner::TestOptions;
 
 pub const PROBLEMS: &[Problem] = macros::include_dir!("chint/problems");
+static LOADED_PROBLEMS: OnceLock<Vec<Problem<'static>>> = OnceLock::new();
 type StaticProblem = &'static Problem<'static>;
 
-fn main() -> anyhow::Result<()> {
-    let command = cli::get_args();
+/// Problems loaded with [`load_problems`], or the embedded [`PROBLEMS`] if nothing was loaded
+pub fn problems() -> &'static [Problem<'static>] {
+    LOADED_PROBLEMS.get().map_or(PROBLEMS, Vec::as_slice)
+}
+
+/// Replace the embedded problems with the ones in `dir`
+pub fn load_problems(dir: &Path) -> anyhow::Result<()> {
+    let problems = macro_types::load_problems(dir, |_| {})
+        .with_context(|| format!("Unable to load problems from {}", dir.display()))?;
+    if LOADED_PROBLEMS.set(problems).is_err() {
+        bail!("Problems are already loaded");
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    process::handle_interrupts();
+    let command = cli::get_args()?;
 
     match command {
-        Command::List => list(),
+        Command::List { options } => list::list(&options)?,
         Command::Show { problem } => show(problem),
+        Command::Init {
+            problem,
+            language,
+            dir,
+        } => init::init(problem, &language, &dir)?,
+        Command::Stats => stats::stats()?,
+        Command::Regen {
+            problems,
+            dir,
+            options,
+        } => {
+            for problem in problems {
+                regen::regen(problem, &regen::problem_dir(&dir, problem)?, &options)?;
+            }
+        }
+        Command::Stress {
+            problem,
+            generator,
+            reference,
+            solution,
+            options,
+        } => {
+            let failed = stress::stress(
+                problem,
+                generator.as_deref(),
+                reference.as_deref(),
+                &solution,
+                &options,
+            )?;
+            if let Some(failed) = failed {
+                return Ok(ExitCode::from(failed.status.exit_code()));
+            }
+        }
         Command::Test {
             problem,
             spec,
-            timeout,
+            options,
+            report,
+        } => {
+            return test(problem, spec, &options, report);
+        }
+        Command::Watch {
+            problem,
+            file,
+            options,
+            report,
         } => {
-            test(problem, spec, timeout)?;
+            watch::watch(&file.clone(), move |cancel| {
+                let options = TestOptions {
+                    cancel,
+                    ..options.clone()
+                };
+                let spec = SolutionSpec::File(file.clone());
+                match test(problem, spec, &options, report.clone()) {
+                    Err(e) if !e.is::<test_runner::Cancelled>() => eprintln!("Error: {e:?}"),
+                    _ => {}
+                }
+            })?;
         }
     };
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
-fn list() {
-    for (i, problem) in PROBLEMS.iter().enumerate() {
-        println!(
-            "{}: {} (tests: {})",
-            i + 1,
-            problem.title.strip_prefix('#').unwrap_or(problem.title),
-            problem.tests.len()
-        );
+fn show(problem: StaticProblem) {
+    let mut details = vec![];
+    if let Some(difficulty) = problem.difficulty {
+        details.push(format!("**Difficulty:** {difficulty}"));
+    }
+    if !problem.tags.is_empty() {
+        details.push(format!("**Tags:** {}", problem.tags.join(", ")));
+    }
+    if let Some(time_limit) = problem.time_limit {
+        details.push(format!("**Time limit:** {}s", time_limit.as_secs_f64()));
+    }
+    if let Some(memory_limit) = problem.memory_limit {
+        details.push(format!("**Memory limit:** {memory_limit} MB"));
+    }
+    if let Some(author) = &problem.author {
+        details.push(format!("**Author:** {author}"));
+    }
+    if let Some(source) = &problem.source {
+        details.push(format!("**Source:** {source}"));
     }
-}
 
-fn show(problem: StaticProblem) {
-    termimad::print_text(&(problem.title.to_string() + "\n" + problem.description));
+    let mut text = format!("# {}\n{}", problem.title, problem.description);
+    if !details.is_empty() {
+        text += &format!("\n\n---\n{}\n", details.join("\n"));
+    }
+    termimad::print_text(&text);
 }
 
-fn test(problem: StaticProblem, spec: SolutionSpec, timeout: Duration) -> anyhow::Result<()> {
-    let command = match spec {
-        cli::SolutionSpec::File(f) => command_guesser::guess_command(&f)
-            .context("Unsupported file format, please provide full command using -c arg")?,
-        cli::SolutionSpec::Command(c) => c,
-    };
-    let result = test_runner::test_problem(problem, &command, timeout);
-    let success = match result {
-        Ok(x) => x,
-        Err(e) => {
-            bail!("Got error while running the tests: {:?}", e);
+/// returns exit code based on the first failed test, see [`cli::EXIT_CODES_HELP`]
+fn test(
+    problem: StaticProblem,
+    spec: SolutionSpec,
+    options: &TestOptions,
+    report: Option<(ReportFormat, PathBuf)>,
+) -> anyhow::Result<ExitCode> {
+    let mut options = options.clone();
+    let prepared = match spec {
+        cli::SolutionSpec::File(f) => {
+            options.visible.push(f.clone());
+            language::by_file(&f)?
+                .context("Unsupported file format, please provide full command using -c arg")?
+                .prepare(&f)?
         }
+        cli::SolutionSpec::Command(c) => Ok(c),
     };
-    if success {
-        println!("Hooray!!");
-    } else {
-        println!("Try just once more!!");
+    let (command, results) = match prepared {
+        Ok(command) => match test_runner::test_problem(problem, &command, &options) {
+            Ok(results) => (command, results),
+            Err(e) => return Err(e.context("Got error while running the tests")),
+        },
+        Err(e) => (e.command, test_runner::compilation_failed(e.output)?),
+    };
+    if let Some((format, path)) = report {
+        report::write_report(format, &path, problem, &command, &results)?;
     }
-    Ok(())
+    let failed = results.iter().find(|r| !r.status.is_success());
+    if let Err(e) = record_progress(problem, &command, &results) {
+        eprintln!("Unable to save progress: {e:#}");
+    }
+    match failed {
+        None => {
+            println!("Hooray!!");
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(failed) => {
+            println!("Try just once more!!");
+            Ok(ExitCode::from(failed.status.exit_code()))
+        }
+    }
+}
+
+fn record_progress(
+    problem: StaticProblem,
+    command: &str,
+    results: &[test_runner::Result],
+) -> anyhow::Result<()> {
+    let mut progress = progress::Progress::load()?;
+    progress.record(&problem.slug, command, results);
+    progress.save()
 }
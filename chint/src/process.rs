@@ -0,0 +1,504 @@
+//! Running solutions with resource limits, Linux only for now
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the process is checked while waiting for it
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Exit code of chint when it is interrupted, like shells use for SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Set when chint is interrupted, e.g. with Ctrl-C
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Number of solutions running, chint exits after an interrupt once it is zero
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Makes SIGINT and SIGTERM kill the running solutions before chint exits. They are in their
+/// own process groups, so they don't get the Ctrl-C from the terminal themselves.
+pub fn handle_interrupts() {
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        if RUNNING.load(Ordering::SeqCst) == 0 {
+            // SAFETY: _exit is async-signal-safe
+            unsafe { libc::_exit(INTERRUPTED_EXIT_CODE) };
+        }
+    }
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only uses atomics and _exit, which are async-signal-safe
+        unsafe {
+            libc::signal(
+                signal,
+                on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+}
+
+pub struct Limits {
+    pub wall_time: Duration,
+    /// User + system time
+    pub cpu_time: Option<Duration>,
+    /// Peak resident memory in bytes
+    pub memory: Option<u64>,
+    /// Bytes of stdout, stderr is cut off at the same size
+    pub output: Option<u64>,
+    /// The process is killed when this is set, e.g. when the tests are restarted
+    pub cancel: Arc<AtomicBool>,
+    /// Pins the process to this CPU, so that parallel tests don't slow each other down. Only
+    /// on Linux, ignored elsewhere
+    pub cpu: Option<usize>,
+    /// Run the process in the [`sandbox`](crate::sandbox)
+    pub sandbox: bool,
+    /// Files the process needs that the sandbox might hide, like the solution's source
+    pub visible: Vec<PathBuf>,
+}
+
+impl Limits {
+    /// Only a wall-clock time limit
+    pub fn with_wall_time(wall_time: Duration) -> Self {
+        Self {
+            wall_time,
+            cpu_time: None,
+            memory: None,
+            output: None,
+            cancel: Arc::default(),
+            cpu: None,
+            sandbox: false,
+            visible: vec![],
+        }
+    }
+}
+
+pub enum Termination {
+    Exited(ExitStatus),
+    TimedOut,
+    MemoryLimitExceeded,
+    OutputLimitExceeded,
+    Cancelled,
+}
+
+pub struct Usage {
+    pub wall_time: Duration,
+    /// User + system time
+    pub cpu_time: Duration,
+    /// Peak resident memory in bytes
+    pub peak_memory: u64,
+}
+
+/// Makes `command` spawn its process with rlimits matching `limits`.
+///
+/// Memory is mostly enforced by [`wait`], the data segment limit is only a safety net
+/// against a single huge allocation, so it is twice the memory limit.
+pub fn apply_limits(command: &mut Command, limits: &Limits) {
+    if let Some(memory) = limits.memory {
+        let data_limit = memory.saturating_mul(2);
+        // SAFETY: setrlimit is async-signal-safe
+        unsafe {
+            command.pre_exec(move || {
+                let limit = libc::rlimit {
+                    rlim_cur: data_limit,
+                    rlim_max: data_limit,
+                };
+                if libc::setrlimit(libc::RLIMIT_DATA, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(cpu) = limits.cpu {
+        // SAFETY: sched_setaffinity is async-signal-safe, the CPU_* functions only touch `set`
+        unsafe {
+            command.pre_exec(move || {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                libc::CPU_SET(cpu, &mut set);
+                if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+/// CPUs that this process is allowed to run on
+#[cfg(target_os = "linux")]
+pub fn available_cpus() -> io::Result<Vec<usize>> {
+    // SAFETY: all zeroes is a valid cpu_set_t, the pointer is valid for the duration of the
+    // call
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect())
+    }
+}
+
+/// CPUs that this process is allowed to run on, assumed to be all of them
+#[cfg(not(target_os = "linux"))]
+pub fn available_cpus() -> io::Result<Vec<usize>> {
+    Ok((0..std::thread::available_parallelism()?.get()).collect())
+}
+
+pub struct Output {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Keeps chint from exiting on an interrupt before the process is killed, see
+/// [`handle_interrupts`]
+pub struct Running(());
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        // The last process to stop after an interrupt
+        if RUNNING.fetch_sub(1, Ordering::SeqCst) == 1 && INTERRUPTED.load(Ordering::SeqCst) {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    }
+}
+
+/// Spawns `command` in a new process group, the whole group is killed by [`wait`]. The
+/// process should be waited for before [`Running`] is dropped.
+pub fn spawn(command: &mut Command) -> io::Result<(Child, Running)> {
+    command.process_group(0);
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    let running = Running(());
+    Ok((command.spawn()?, running))
+}
+
+/// Runs `command` with `input` as stdin, see [`spawn`] and [`communicate`]
+pub fn run(
+    command: &mut Command,
+    input: &[u8],
+    limits: &Limits,
+) -> io::Result<(Termination, Usage, Output)> {
+    let (mut child, _running) = spawn(command)?;
+    communicate(&mut child, input, limits)
+}
+
+/// Writes `input` to the stdin of `child` and reads its stdout and stderr while waiting for it
+/// to exit, all at the same time so that the child can't get stuck on a full pipe
+fn communicate(
+    child: &mut Child,
+    input: &[u8],
+    limits: &Limits,
+) -> io::Result<(Termination, Usage, Output)> {
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take().expect("stdout should be piped");
+    let stderr = child.stderr.take().expect("stderr should be piped");
+    let limit = limits.output.unwrap_or(u64::MAX);
+    let output_exceeded = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || match stdin {
+            // The solution doesn't have to read all of the input
+            Some(mut stdin) => match stdin.write_all(input) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            },
+            None => Ok(()),
+        });
+        let stdout = scope.spawn(|| read_bounded(stdout, limit, Some(&output_exceeded)));
+        let stderr = scope.spawn(|| read_bounded(stderr, limit, None));
+
+        let waited = wait(child, limits, &output_exceeded);
+        if waited.is_err() {
+            // Otherwise the threads might never finish
+            let _ = kill_group(child);
+        }
+        let output = Output {
+            stdout: join(stdout)?,
+            stderr: join(stderr)?,
+        };
+        join(writer)?;
+        let (termination, usage) = waited?;
+        Ok((output_checked(termination, &output_exceeded), usage, output))
+    })
+}
+
+/// `termination` of a process that exited, or [`Termination::OutputLimitExceeded`] if
+/// `output_exceeded` was set. It can write everything and exit before it is noticed by
+/// [`wait`].
+pub fn output_checked(termination: Termination, output_exceeded: &AtomicBool) -> Termination {
+    match termination {
+        Termination::Exited(_) if output_exceeded.load(Ordering::Relaxed) => {
+            Termination::OutputLimitExceeded
+        }
+        termination => termination,
+    }
+}
+
+/// Waits for a thread, panicking like it did
+pub fn join<T>(thread: thread::ScopedJoinHandle<T>) -> T {
+    thread
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+}
+
+/// Reads `source` to the end and returns the first `limit` bytes, sets `exceeded` if there
+/// were more
+pub fn read_bounded(
+    mut source: impl Read,
+    limit: u64,
+    exceeded: Option<&AtomicBool>,
+) -> io::Result<Vec<u8>> {
+    let mut kept = vec![];
+    let mut buffer = [0; 8192];
+    loop {
+        let read = match source.read(&mut buffer) {
+            Ok(0) => return Ok(kept),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let room = limit.saturating_sub(kept.len() as u64).min(read as u64) as usize;
+        kept.extend_from_slice(&buffer[..room]);
+        if room < read {
+            if let Some(exceeded) = exceeded {
+                exceeded.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Waits for `child` to exit, killing its process group if it goes over the limits or
+/// `output_exceeded` is set, and once it exited
+pub fn wait(
+    child: &mut Child,
+    limits: &Limits,
+    output_exceeded: &AtomicBool,
+) -> io::Result<(Termination, Usage)> {
+    let start = Instant::now();
+    let pid = child.id() as libc::pid_t;
+    let mut killed_for = None;
+    loop {
+        // Only waits for the exit without reaping the process, so that its id and the id of
+        // its group can't be reused before the group is killed
+        let flags = libc::WEXITED
+            | libc::WNOWAIT
+            | if killed_for.is_some() {
+                0
+            } else {
+                libc::WNOHANG
+            };
+        // SAFETY: all zeroes is a valid siginfo_t
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        // SAFETY: the pointer is valid for the duration of the call
+        let r = unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, flags) };
+        if r == -1 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        // Still zeroed if it is running
+        if info.si_signo == libc::SIGCHLD {
+            // Processes it started could still have the pipes open
+            kill_group(child)?;
+            let (status, rusage) = reap(pid)?;
+            let usage = Usage {
+                wall_time: start.elapsed(),
+                cpu_time: to_duration(rusage.ru_utime) + to_duration(rusage.ru_stime),
+                // ru_maxrss is in kilobytes on Linux
+                peak_memory: rusage.ru_maxrss as u64 * 1024,
+            };
+            // The process can go over the limits between two checks
+            let termination = match killed_for {
+                Some(termination) => termination,
+                None if limits.cpu_time.is_some_and(|limit| usage.cpu_time > limit) => {
+                    Termination::TimedOut
+                }
+                None if limits.memory.is_some_and(|limit| usage.peak_memory > limit) => {
+                    Termination::MemoryLimitExceeded
+                }
+                None => Termination::Exited(ExitStatus::from_raw(status)),
+            };
+            return Ok((termination, usage));
+        }
+
+        let over_cpu_time = || {
+            limits
+                .cpu_time
+                .is_some_and(|limit| tree_cpu_time(pid) > limit)
+        };
+        if limits.cancel.load(Ordering::Relaxed) || INTERRUPTED.load(Ordering::SeqCst) {
+            killed_for = Some(Termination::Cancelled);
+        } else if output_exceeded.load(Ordering::Relaxed) {
+            killed_for = Some(Termination::OutputLimitExceeded);
+        } else if start.elapsed() >= limits.wall_time || over_cpu_time() {
+            killed_for = Some(Termination::TimedOut);
+        } else if limits
+            .memory
+            .is_some_and(|limit| tree_resident_memory(pid) > limit)
+        {
+            killed_for = Some(Termination::MemoryLimitExceeded);
+        }
+        match killed_for {
+            // The process is waited for by the blocking waitid in the next iteration
+            Some(_) => kill_group(child)?,
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Reaps the exited process `pid`, returns its wait status and resource usage
+fn reap(pid: libc::pid_t) -> io::Result<(i32, libc::rusage)> {
+    let mut status = 0;
+    // SAFETY: all zeroes is a valid rusage
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: pointers are valid for the duration of the call
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) } != -1 {
+            return Ok((status, rusage));
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+/// Kills the process group started by [`spawn`], its id is the id of the first process
+pub fn kill_group(child: &Child) -> io::Result<()> {
+    // SAFETY: kill has no preconditions
+    if unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) } == -1 {
+        let error = io::Error::last_os_error();
+        // Everything exited already
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+fn to_duration(time: libc::timeval) -> Duration {
+    Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+}
+
+/// `pid` and its descendants that are still running, solutions can be started by a wrapper
+/// like a shell or the sandbox
+fn process_tree(pid: libc::pid_t) -> Vec<libc::pid_t> {
+    let mut tree = vec![pid];
+    let mut i = 0;
+    while i < tree.len() {
+        // Children are listed by the thread that started them
+        if let Ok(tasks) = fs::read_dir(format!("/proc/{}/task", tree[i])) {
+            for task in tasks.flatten() {
+                if let Ok(children) = fs::read_to_string(task.path().join("children")) {
+                    tree.extend(
+                        children
+                            .split_whitespace()
+                            .filter_map(|c| c.parse::<libc::pid_t>().ok()),
+                    );
+                }
+            }
+        }
+        i += 1;
+    }
+    tree
+}
+
+/// User + system time used so far by a running process and its descendants
+fn tree_cpu_time(pid: libc::pid_t) -> Duration {
+    process_tree(pid).into_iter().filter_map(cpu_time).sum()
+}
+
+/// User + system time used so far by a running process, including its children that exited
+fn cpu_time(pid: libc::pid_t) -> Option<Duration> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name can contain spaces, fields after it are space separated
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace();
+    // utime, stime, cutime and cstime are the 14th to 17th fields, the first two are before
+    // ')'
+    let mut ticks = 0;
+    for field in fields.by_ref().skip(11).take(4) {
+        ticks += field.parse::<u64>().ok()?;
+    }
+    // SAFETY: sysconf has no preconditions
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as f64;
+    Some(Duration::from_secs_f64(ticks as f64 / ticks_per_second))
+}
+
+/// Resident memory of a running process and its descendants in bytes
+fn tree_resident_memory(pid: libc::pid_t) -> u64 {
+    process_tree(pid)
+        .into_iter()
+        .filter_map(resident_memory)
+        .sum()
+}
+
+/// Current resident memory of a running process in bytes
+fn resident_memory(pid: libc::pid_t) -> Option<u64> {
+    let statm = fs::read_to_string(format!("/proc/{pid}/statm")).ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size as u64)
+}
+
+#[test]
+fn large_output() {
+    use std::process::Stdio;
+
+    let run = |output_limit: u64| {
+        // Writes more than a pipe holds before it reads anything
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", "head -c 1000000 /dev/zero; cat"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let limits = Limits {
+            output: Some(output_limit),
+            ..Limits::with_wall_time(Duration::from_secs(10))
+        };
+        run(&mut command, &[b'a'; 1_000_000], &limits).unwrap()
+    };
+
+    let (termination, _, output) = run(2_000_000);
+    assert!(matches!(termination, Termination::Exited(status) if status.success()));
+    assert_eq!(output.stdout.len(), 2_000_000);
+
+    let (termination, _, output) = run(1_500_000);
+    assert!(matches!(termination, Termination::OutputLimitExceeded));
+    assert_eq!(output.stdout.len(), 1_500_000);
+}
+
+#[test]
+fn kill_process_group() {
+    use std::process::Stdio;
+
+    let run = |script: &str, wall_time: Duration| {
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", script])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        run(&mut command, b"", &Limits::with_wall_time(wall_time)).unwrap()
+    };
+    let start = Instant::now();
+
+    // The sleeping child keeps stdout open, so reading it would hang if it wasn't killed
+    let (termination, _, _) = run("sleep 100 & sleep 100", Duration::from_millis(200));
+    assert!(matches!(termination, Termination::TimedOut));
+
+    let (termination, _, output) = run("sleep 100 & echo done", Duration::from_secs(10));
+    assert!(matches!(termination, Termination::Exited(status) if status.success()));
+    assert_eq!(output.stdout, b"done\n");
+
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,37 @@
+use std::fs::{self, Permissions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use macro_types::Program;
+use tempfile::TempDir;
+
+use crate::language;
+
+/// A [`Program`] written to a temporary directory, so that it can be run
+pub struct PreparedProgram {
+    pub command: String,
+    /// The program's file in [`PreparedProgram::dir`]
+    pub path: PathBuf,
+    /// Removed when the program is dropped, can be used for the program's scratch files
+    pub dir: TempDir,
+}
+
+impl PreparedProgram {
+    pub fn new(program: &Program) -> anyhow::Result<Self> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(program.file_name.as_ref());
+        fs::write(&path, &program.source)?;
+        fs::set_permissions(&path, Permissions::from_mode(0o755))?;
+
+        // Files without a known extension are run directly, e.g. with a shebang
+        let command = match language::by_file(&path)? {
+            Some(language) => match language.prepare(&path)? {
+                Ok(command) => command,
+                Err(e) => bail!("Unable to compile {}:\n{}", program.file_name, e.output),
+            },
+            None => shlex::join([path.to_str().context("Temporary path is not UTF-8")?]),
+        };
+        Ok(Self { command, path, dir })
+    }
+}
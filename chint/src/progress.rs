@@ -0,0 +1,184 @@
+//! History of `chint test` runs, in `$XDG_DATA_HOME/chint`
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Days, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::test_runner;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// Oldest first
+    #[serde(default)]
+    pub runs: Vec<Run>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Run {
+    /// Problem slug
+    pub problem: String,
+    pub timestamp: DateTime<Utc>,
+    pub command: String,
+    pub tests: Vec<TestRun>,
+    /// All the tests passed
+    pub solved: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TestRun {
+    pub name: String,
+    pub verdict: String,
+    /// In seconds
+    pub time: f64,
+}
+
+/// Summary of the runs of a problem
+#[derive(Clone, Default)]
+pub struct ProblemProgress {
+    pub attempts: u64,
+    pub solved: bool,
+    /// Number of attempts up to and including the first solved one
+    pub attempts_to_solve: Option<u64>,
+    /// Time taken by the slowest test, in the fastest solved run
+    pub best_time: Option<Duration>,
+}
+
+impl Run {
+    fn slowest_test(&self) -> Duration {
+        let time = self.tests.iter().map(|t| t.time).fold(0.0, f64::max);
+        Duration::from_secs_f64(time)
+    }
+}
+
+impl Progress {
+    /// Empty if nothing was recorded yet
+    pub fn load() -> anyhow::Result<Self> {
+        let path = path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid progress file {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Unable to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = path()?;
+        let dir = path.parent().unwrap();
+        fs::create_dir_all(dir).with_context(|| format!("Unable to create {}", dir.display()))?;
+        // Write to a temporary file first, so that an interrupted write can't lose the progress
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Unable to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, &path)
+            .with_context(|| format!("Unable to write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn get(&self, slug: &str) -> Option<ProblemProgress> {
+        let mut progress: Option<ProblemProgress> = None;
+        for run in self.runs.iter().filter(|run| run.problem == slug) {
+            let progress = progress.get_or_insert_with(Default::default);
+            progress.attempts += 1;
+            if run.solved {
+                progress.solved = true;
+                progress.attempts_to_solve.get_or_insert(progress.attempts);
+                let time = run.slowest_test();
+                progress.best_time = Some(progress.best_time.map_or(time, |t| t.min(time)));
+            }
+        }
+        progress
+    }
+
+    pub fn record(&mut self, slug: &str, command: &str, results: &[test_runner::Result]) {
+        self.runs.push(Run {
+            problem: slug.to_string(),
+            timestamp: Utc::now(),
+            command: command.to_string(),
+            tests: results
+                .iter()
+                .map(|r| TestRun {
+                    name: r.test_name.clone(),
+                    verdict: r.status.verdict().to_string(),
+                    time: r.time_taken.as_secs_f64(),
+                })
+                .collect(),
+            solved: results.iter().all(|r| r.status.is_success()),
+        });
+    }
+
+    /// Local dates with at least one run, in order
+    pub fn active_days(&self) -> BTreeSet<NaiveDate> {
+        self.runs
+            .iter()
+            .map(|run| run.timestamp.with_timezone(&Local).date_naive())
+            .collect()
+    }
+}
+
+/// Longest number of consecutive days in `days`, and the number of consecutive days up to
+/// `today` (or yesterday, so that the streak isn't lost before practising today)
+pub fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u64, u64) {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        let consecutive = previous.and_then(|p| p.checked_add_days(Days::new(1))) == Some(day);
+        current = if consecutive { current + 1 } else { 1 };
+        longest = longest.max(current);
+        previous = Some(day);
+    }
+    let yesterday = today.checked_sub_days(Days::new(1));
+    let ongoing = previous == Some(today) || previous == yesterday;
+    (longest, if ongoing { current } else { 0 })
+}
+
+fn path() -> anyhow::Result<PathBuf> {
+    let dir = dirs::data_dir().context("Unable to find the data directory")?;
+    Ok(dir.join("chint").join("progress.json"))
+}
+
+#[test]
+fn day_streaks() {
+    let day = |d| NaiveDate::from_ymd_opt(2023, 5, d).unwrap();
+    let days = [1, 2, 3, 7, 8].into_iter().map(day).collect();
+
+    assert_eq!(streaks(&days, day(8)), (3, 2));
+    assert_eq!(streaks(&days, day(9)), (3, 2));
+    assert_eq!(streaks(&days, day(10)), (3, 0));
+    assert_eq!(streaks(&BTreeSet::new(), day(10)), (0, 0));
+}
+
+#[test]
+fn summarize_runs() {
+    let run = |problem: &str, solved, time| Run {
+        problem: problem.to_string(),
+        timestamp: Utc::now(),
+        command: "python3 sol.py".to_string(),
+        tests: vec![TestRun {
+            name: "1".to_string(),
+            verdict: "Success".to_string(),
+            time,
+        }],
+        solved,
+    };
+    let progress = Progress {
+        runs: vec![
+            run("two-sum", false, 0.5),
+            run("two-sum", true, 0.3),
+            run("two-sum", true, 0.2),
+        ],
+    };
+
+    let problem = progress.get("two-sum").unwrap();
+    assert_eq!(problem.attempts, 3);
+    assert!(problem.solved);
+    assert_eq!(problem.attempts_to_solve, Some(2));
+    assert_eq!(problem.best_time, Some(Duration::from_secs_f64(0.2)));
+    assert!(progress.get("hello-world").is_none());
+}
@@ -0,0 +1,122 @@
+//! `chint problem regen`, making the expected outputs of a problem with its reference solution
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use macro_types::Problem;
+
+use crate::program::PreparedProgram;
+use crate::test_runner::{self, TestOptions};
+
+/// The directory of `problem` in `root`, named `<id>` or `<id>-<slug>`
+pub fn problem_dir(root: &Path, problem: &Problem) -> anyhow::Result<PathBuf> {
+    let id = problem.id.to_string();
+    for entry in root
+        .read_dir()
+        .with_context(|| format!("Unable to read {}", root.display()))?
+    {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if path.is_dir() && (name == id || name.starts_with(&format!("{id}-"))) {
+            return Ok(path);
+        }
+    }
+    bail!(
+        "No directory for problem {} in {}",
+        problem.id,
+        root.display()
+    )
+}
+
+/// Runs the reference solution of `problem` on each test and writes its output to the
+/// test's `.out` file in `dir` if it is different
+pub fn regen(problem: &Problem, dir: &Path, options: &TestOptions) -> anyhow::Result<()> {
+    if problem.interactor.is_some() {
+        bail!(
+            "{} is interactive, its expected outputs are for the interactor",
+            problem.slug
+        );
+    }
+    let reference = problem
+        .reference
+        .as_ref()
+        .with_context(|| format!("{} has no reference solution", problem.slug))?;
+    let reference =
+        PreparedProgram::new(reference).context("Unable to prepare the reference solution")?;
+
+    let mut changed = 0;
+    for test in problem.tests.iter() {
+        let limits = options.limits(problem, test);
+        let result = test_runner::run_program(test, &reference.command, &limits)?;
+        if !result.status.is_success() {
+            bail!(
+                "Reference solution failed on test {}: {}\n{}",
+                test.test_name,
+                result.status.verdict(),
+                result.stderr
+            );
+        }
+        if result.stdout.as_bytes() == test.output.as_ref() {
+            continue;
+        }
+        let path = dir.join(format!("{}.out", test.test_name));
+        fs::write(&path, &result.stdout)
+            .with_context(|| format!("Unable to write {}", path.display()))?;
+        println!("Updated {}", path.display());
+        changed += 1;
+    }
+    println!(
+        "{}: {changed} of {} outputs changed",
+        problem.slug,
+        problem.tests.len()
+    );
+    Ok(())
+}
+
+#[test]
+fn regen_outputs() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+
+    let root = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = root.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    };
+    write("1-double/description.md", "# Double\n");
+    write("1-double/1.in", "1\n");
+    let correct = write("1-double/1.out", "2\n");
+    write("1-double/2.in", "5\n");
+    let stale = write("1-double/2.out", "9\n");
+    let reference = write("1-double/reference", "#!/bin/sh\nread n\necho $((n * 2))\n");
+    fs::set_permissions(reference, fs::Permissions::from_mode(0o755)).unwrap();
+    let old = SystemTime::now() - Duration::from_secs(3600);
+    fs::File::options()
+        .write(true)
+        .open(&correct)
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+    write("2-guess/description.md", "# Guess\n");
+    write("2-guess/1.in", "1\n");
+    write("2-guess/1.out", "1\n");
+    write("2-guess/reference", "#!/bin/sh\necho 1\n");
+    write("2-guess/interactor", "#!/bin/sh\necho 1\n");
+    let problems = macro_types::load_problems(root.path(), |_| {}).unwrap();
+    let options = TestOptions::default();
+
+    let dir = problem_dir(root.path(), &problems[0]).unwrap();
+    regen(&problems[0], &dir, &options).unwrap();
+    assert_eq!(fs::read_to_string(&stale).unwrap(), "10\n");
+    assert_eq!(fs::read_to_string(&correct).unwrap(), "2\n");
+    assert_eq!(fs::metadata(&correct).unwrap().modified().unwrap(), old);
+
+    let dir = problem_dir(root.path(), &problems[1]).unwrap();
+    let err = regen(&problems[1], &dir, &options).unwrap_err();
+    assert!(err.to_string().contains("is interactive"), "{err}");
+    assert_eq!(fs::read_to_string(dir.join("1.out")).unwrap(), "1\n");
+}
@@ -0,0 +1,202 @@
+//! Machine readable reports of test results, e.g. for CI
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use clap::ValueEnum;
+use macro_types::Problem;
+use serde::{Serialize, Serializer};
+
+use crate::test_runner::{self, Result, ResultStatus};
+
+/// Outputs of solutions are truncated to this many bytes in reports
+const MAX_OUTPUT_LEN: usize = 16 * 1024;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Json,
+    /// JUnit XML
+    Junit,
+}
+
+impl ReportFormat {
+    pub fn default_path(self) -> PathBuf {
+        match self {
+            ReportFormat::Json => "chint-report.json".into(),
+            ReportFormat::Junit => "chint-report.xml".into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    /// Stable keys of the problem, to match reports of different runs
+    id: u64,
+    slug: &'a str,
+    /// Title of the problem
+    problem: &'a str,
+    command: &'a str,
+    success: bool,
+    tests: &'a [Result],
+}
+
+pub fn write_report(
+    format: ReportFormat,
+    path: &Path,
+    problem: &Problem,
+    command: &str,
+    results: &[Result],
+) -> anyhow::Result<()> {
+    let title = problem.title.as_ref();
+    let content = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&JsonReport {
+            id: problem.id,
+            slug: &problem.slug,
+            problem: title,
+            command,
+            success: results.iter().all(|r| r.status.is_success()),
+            tests: results,
+        })?,
+        ReportFormat::Junit => junit(title, results),
+    };
+    fs::write(path, content)
+        .with_context(|| format!("Unable to write report to {}", path.display()))
+}
+
+fn junit(problem: &str, results: &[Result]) -> String {
+    let failures = results.iter().filter(|r| !r.status.is_success()).count();
+    let time: f64 = results.iter().map(|r| r.time_taken.as_secs_f64()).sum();
+    let problem = escape(problem);
+
+    let mut xml = String::new();
+    // Writing to a String can't fail
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<testsuites tests="{}" failures="{failures}" time="{time:.4}">"#,
+        results.len()
+    );
+    let _ = writeln!(
+        xml,
+        r#"  <testsuite name="{problem}" tests="{}" failures="{failures}" time="{time:.4}">"#,
+        results.len()
+    );
+    for result in results {
+        let _ = writeln!(
+            xml,
+            r#"    <testcase name="{}" classname="{problem}" time="{:.4}">"#,
+            escape(&result.test_name),
+            result.time_taken.as_secs_f64()
+        );
+        if !result.status.is_success() {
+            let _ = writeln!(
+                xml,
+                r#"      <failure type="{}" message="{}"/>"#,
+                result.status.verdict(),
+                escape(&failure_message(&result.status))
+            );
+        }
+        // The output of interactive solutions is in the transcript
+        let stdout = match result.transcript.is_empty() {
+            true => &result.stdout,
+            false => &result.transcript,
+        };
+        if !stdout.is_empty() {
+            let _ = writeln!(
+                xml,
+                "      <system-out>{}</system-out>",
+                escape(&truncate(stdout))
+            );
+        }
+        if !result.stderr.is_empty() {
+            let _ = writeln!(
+                xml,
+                "      <system-err>{}</system-err>",
+                escape(&truncate(&result.stderr))
+            );
+        }
+        let _ = writeln!(xml, "    </testcase>");
+    }
+    let _ = writeln!(xml, "  </testsuite>");
+    let _ = writeln!(xml, "</testsuites>");
+    xml
+}
+
+fn failure_message(status: &ResultStatus) -> String {
+    match status {
+        ResultStatus::Success => String::new(),
+        ResultStatus::IncorrectExitCode { exit_code } => format!("Exited with {exit_code}"),
+        ResultStatus::RuntimeError { signal, .. } => {
+            let (name, explanation) = test_runner::describe_signal(*signal);
+            format!("Killed by {name} (signal {signal}): {explanation}")
+        }
+        ResultStatus::IncorrectOutput { mismatch } => mismatch.to_string(),
+        ResultStatus::CheckerRejected { message } => message.clone(),
+        ResultStatus::Timeout => "Timed out".to_string(),
+        ResultStatus::MemoryLimitExceeded => "Memory limit exceeded".to_string(),
+        ResultStatus::CompilationError => "Compilation error".to_string(),
+        ResultStatus::SecurityViolation { reason } => reason.clone(),
+        ResultStatus::OutputLimitExceeded => "Output limit exceeded".to_string(),
+    }
+}
+
+/// Escapes text for XML, dropping characters that XML doesn't allow
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn truncate(text: &str) -> Cow<'_, str> {
+    if text.len() <= MAX_OUTPUT_LEN {
+        return Cow::Borrowed(text);
+    }
+    let mut end = MAX_OUTPUT_LEN;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!(
+        "{}\n... ({} more bytes)",
+        &text[..end],
+        text.len() - end
+    ))
+}
+
+pub fn serialize_truncated<S: Serializer>(
+    text: &str,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&truncate(text))
+}
+
+pub fn serialize_secs<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+#[test]
+fn escape_xml() {
+    assert_eq!(
+        escape("<a href=\"x\">&\u{1b}[0m"),
+        "&lt;a href=&quot;x&quot;&gt;&amp;[0m"
+    );
+    let long = "é".repeat(MAX_OUTPUT_LEN);
+    assert_eq!(truncate("short"), "short");
+    assert!(truncate(&long).ends_with("é\n... (16384 more bytes)"));
+}
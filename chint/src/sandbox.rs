@@ -0,0 +1,521 @@
+//! `chint test --sandbox`, running untrusted solutions isolated from the rest of the system.
+//!
+//! The solution runs in new user, mount, network, PID, IPC and UTS namespaces, with a
+//! read-only view of the filesystem, an empty tmpfs on `/tmp`, no network, limits on the
+//! number of processes and the size of files, and a seccomp filter against system calls that
+//! could be used to get out of the sandbox.
+//!
+//! The spawned process sets up the namespaces and stays outside the new PID namespace, its
+//! child is the init of the namespace and the solution is the grandchild. When the spawned
+//! process is killed the whole namespace goes with it, and it exits like the solution did.
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use crate::language;
+
+/// Files written by the solution can't be bigger than this, and neither can `/tmp` in total
+const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+/// Processes and threads of the solution
+const MAX_PROCESSES: u64 = 64;
+/// Tmpfs options for the private `/tmp`
+const TMP_OPTIONS: &CStr = c"size=64m,mode=1777";
+
+/// System calls that kill the solution
+const FORBIDDEN_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_open_tree,
+    libc::SYS_move_mount,
+    libc::SYS_fsopen,
+    libc::SYS_fsmount,
+    libc::SYS_mount_setattr,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_socket,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_userfaultfd,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_kexec_load,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+];
+
+/// `clone` with any of these flags kills the solution
+const NAMESPACE_FLAGS: libc::c_int = libc::CLONE_NEWUSER
+    | libc::CLONE_NEWNS
+    | libc::CLONE_NEWNET
+    | libc::CLONE_NEWPID
+    | libc::CLONE_NEWIPC
+    | libc::CLONE_NEWUTS
+    | libc::CLONE_NEWCGROUP;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+/// Not used, [`apply`] fails on other architectures
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: u32 = 0;
+
+// Not in libc yet
+const MOUNT_ATTR_RDONLY: u64 = 0x1;
+const MOUNT_ATTR_NOSUID: u64 = 0x2;
+const MOVE_MOUNT_F_EMPTY_PATH: libc::c_uint = 0x4;
+
+#[repr(C)]
+struct MountAttr {
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: u64,
+}
+
+/// Why the sandbox stopped a solution that exited with `status`, if it did
+pub fn violation(status: ExitStatus) -> Option<&'static str> {
+    match status.signal()? {
+        libc::SIGSYS => Some("Used a forbidden system call"),
+        libc::SIGXFSZ => Some("Wrote a file bigger than the limit"),
+        _ => None,
+    }
+}
+
+/// Most paths under `/tmp` that can be kept, see [`apply`]
+const MAX_KEPT: usize = 4;
+
+/// Everything the child needs, prepared before forking because allocating isn't safe after it
+struct Setup {
+    uid_map: CString,
+    gid_map: CString,
+    /// Paths under `/tmp` that are still visible, read-only, after the private `/tmp` is
+    /// mounted over it. Ancestors come first.
+    kept: Vec<Kept>,
+    filter: Vec<libc::sock_filter>,
+}
+
+struct Kept {
+    path: CString,
+    /// Directories to create for it in the new `/tmp`, ancestors first
+    dirs: Vec<CString>,
+    /// A file instead of a directory, an empty one is created to mount it on
+    file: bool,
+}
+
+/// Makes `command` run its program in the sandbox. The directories of `files`, like the
+/// solution's source, stay visible if they are under `/tmp`, or only the files themselves if
+/// they are right in `/tmp`.
+pub fn apply(command: &mut Command, files: &[PathBuf]) -> io::Result<()> {
+    if cfg!(not(any(target_arch = "x86_64", target_arch = "aarch64"))) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The sandbox is not supported on this architecture",
+        ));
+    }
+    // SAFETY: getuid and getgid have no preconditions
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let tmp = Path::new("/tmp");
+    // Compiled solutions, and the solution's working directory which it might look up by path
+    let dirs = [language::build_dir(), std::env::current_dir().ok()]
+        .into_iter()
+        .filter_map(|dir| dir?.canonicalize().ok());
+    let files = files.iter().filter_map(|file| {
+        let file = file.canonicalize().ok()?;
+        match file.parent() {
+            Some(dir) if dir != tmp => Some(dir.to_path_buf()),
+            _ => Some(file),
+        }
+    });
+    let mut paths: Vec<PathBuf> = dirs
+        .chain(files)
+        .filter(|path| path.starts_with(tmp) && path != tmp)
+        .collect();
+    paths.sort_by_key(|path| path.components().count());
+    // Those in a kept directory are visible already
+    let mut kept: Vec<PathBuf> = vec![];
+    for path in paths {
+        if !kept.iter().any(|dir| path.starts_with(dir)) {
+            kept.push(path);
+        }
+    }
+    if kept.len() > MAX_KEPT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too many paths to keep in the sandbox",
+        ));
+    }
+    let setup = Setup {
+        uid_map: cstring(format!("{uid} {uid} 1").as_bytes())?,
+        gid_map: cstring(format!("{gid} {gid} 1").as_bytes())?,
+        kept: kept
+            .iter()
+            .map(|path| {
+                let file = !path.is_dir();
+                let dirs = path
+                    .ancestors()
+                    .skip(file as usize)
+                    .take_while(|dir| *dir != tmp)
+                    .map(path_cstring)
+                    .collect::<io::Result<Vec<_>>>()?;
+                Ok(Kept {
+                    path: path_cstring(path)?,
+                    dirs: dirs.into_iter().rev().collect(),
+                    file,
+                })
+            })
+            .collect::<io::Result<_>>()?,
+        filter: seccomp_filter(),
+    };
+    // SAFETY: only async-signal-safe functions are called in the child, see `enter`
+    unsafe {
+        command.pre_exec(move || enter(&setup));
+    }
+    Ok(())
+}
+
+fn cstring(bytes: &[u8]) -> io::Result<CString> {
+    CString::new(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    cstring(path.as_os_str().as_bytes())
+}
+
+/// Turns -1 into the error in errno
+fn check<T: PartialEq + From<i8>>(result: T) -> io::Result<T> {
+    if result == T::from(-1) {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// Runs in the forked child before exec, returns only in the process that runs the solution
+///
+/// # Safety
+///
+/// Must be called in a forked child of a multi-threaded process, so only async-signal-safe
+/// functions can be used, which excludes allocating.
+unsafe fn enter(setup: &Setup) -> io::Result<()> {
+    // Reproducing the solution's signal shouldn't dump a core
+    let no_core = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    check(libc::setrlimit(libc::RLIMIT_CORE, &no_core))?;
+    check(libc::unshare(
+        libc::CLONE_NEWUSER
+            | libc::CLONE_NEWNS
+            | libc::CLONE_NEWNET
+            | libc::CLONE_NEWPID
+            | libc::CLONE_NEWIPC
+            | libc::CLONE_NEWUTS,
+    ))?;
+    write_file(c"/proc/self/setgroups", b"deny")?;
+    write_file(c"/proc/self/uid_map", setup.uid_map.as_bytes())?;
+    write_file(c"/proc/self/gid_map", setup.gid_map.as_bytes())?;
+    setup_mounts(setup)?;
+
+    // Filled in by the init with the solution's wait status, after setting the first field
+    let shared = check(libc::mmap(
+        std::ptr::null_mut(),
+        2 * std::mem::size_of::<libc::c_int>(),
+        libc::PROT_READ | libc::PROT_WRITE,
+        libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+        -1,
+        0,
+    ) as isize)? as *mut libc::c_int;
+
+    let init = check(libc::fork())?;
+    if init != 0 {
+        // Keeps the pipes of the solution open otherwise, including the one that tells the
+        // parent that exec succeeded
+        libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0);
+        let mut status = 0;
+        while libc::waitpid(init, &mut status, 0) == -1
+            && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted
+        {}
+        if std::ptr::read_volatile(shared) == 1 {
+            exit_like(std::ptr::read_volatile(shared.add(1)));
+        }
+        // The init was killed, this is the wait status of that
+        exit_like(libc::SIGKILL);
+    }
+
+    // The init of the PID namespace, killed along with the spawned process
+    libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL);
+    // Best effort, not allowed in some containers, the solution sees all processes then
+    libc::mount(
+        c"proc".as_ptr(),
+        c"/proc".as_ptr(),
+        c"proc".as_ptr(),
+        libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+        std::ptr::null(),
+    );
+    let solution = check(libc::fork())?;
+    if solution != 0 {
+        libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0);
+        // Reaps orphans too, the namespace is gone once the solution is
+        loop {
+            let mut status = 0;
+            let pid = libc::waitpid(-1, &mut status, 0);
+            if pid == solution {
+                std::ptr::write_volatile(shared.add(1), status);
+                std::ptr::write_volatile(shared, 1);
+                libc::_exit(0);
+            }
+            if pid == -1 && io::Error::last_os_error().raw_os_error() == Some(libc::ECHILD) {
+                libc::_exit(1);
+            }
+        }
+    }
+
+    for (resource, limit) in [
+        (libc::RLIMIT_NPROC, MAX_PROCESSES),
+        (libc::RLIMIT_FSIZE, MAX_FILE_SIZE),
+    ] {
+        let limit = libc::rlimit {
+            rlim_cur: limit,
+            rlim_max: limit,
+        };
+        check(libc::setrlimit(resource, &limit))?;
+    }
+    check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+    let program = libc::sock_fprog {
+        len: setup.filter.len() as libc::c_ushort,
+        filter: setup.filter.as_ptr() as *mut libc::sock_filter,
+    };
+    check(libc::prctl(
+        libc::PR_SET_SECCOMP,
+        libc::SECCOMP_MODE_FILTER,
+        &program as *const libc::sock_fprog,
+    ))?;
+    Ok(())
+}
+
+/// Makes everything read-only and mounts the private `/tmp`
+///
+/// # Safety
+///
+/// Same as [`enter`]
+unsafe fn setup_mounts(setup: &Setup) -> io::Result<()> {
+    // Nothing done here should be visible outside of the namespace
+    check(libc::mount(
+        std::ptr::null(),
+        c"/".as_ptr(),
+        std::ptr::null(),
+        libc::MS_REC | libc::MS_PRIVATE,
+        std::ptr::null(),
+    ))?;
+    let read_only = MountAttr {
+        attr_set: MOUNT_ATTR_RDONLY | MOUNT_ATTR_NOSUID,
+        attr_clr: 0,
+        propagation: 0,
+        userns_fd: 0,
+    };
+    check(libc::syscall(
+        libc::SYS_mount_setattr,
+        libc::AT_FDCWD,
+        c"/".as_ptr(),
+        libc::AT_RECURSIVE,
+        &read_only as *const MountAttr,
+        std::mem::size_of::<MountAttr>(),
+    ))?;
+    // Read-only copies of the kept paths, to mount them again in the new `/tmp`
+    let mut trees = [-1; MAX_KEPT];
+    for (kept, tree) in setup.kept.iter().zip(&mut trees) {
+        *tree = check(libc::syscall(
+            libc::SYS_open_tree,
+            libc::AT_FDCWD,
+            kept.path.as_ptr(),
+            libc::OPEN_TREE_CLONE | libc::OPEN_TREE_CLOEXEC | libc::AT_RECURSIVE as u32,
+        ))? as libc::c_int;
+    }
+    check(libc::mount(
+        c"tmpfs".as_ptr(),
+        c"/tmp".as_ptr(),
+        c"tmpfs".as_ptr(),
+        libc::MS_NOSUID | libc::MS_NODEV,
+        TMP_OPTIONS.as_ptr().cast(),
+    ))?;
+    for (kept, tree) in setup.kept.iter().zip(trees) {
+        for dir in &kept.dirs {
+            // Shared with another kept path
+            if libc::mkdir(dir.as_ptr(), 0o755) == -1
+                && io::Error::last_os_error().kind() != io::ErrorKind::AlreadyExists
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if kept.file {
+            let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_CLOEXEC;
+            libc::close(check(libc::open(kept.path.as_ptr(), flags, 0o644))?);
+        }
+        check(libc::syscall(
+            libc::SYS_move_mount,
+            tree,
+            c"".as_ptr(),
+            libc::AT_FDCWD,
+            kept.path.as_ptr(),
+            MOVE_MOUNT_F_EMPTY_PATH,
+        ))?;
+        libc::close(tree);
+    }
+    Ok(())
+}
+
+/// # Safety
+///
+/// Same as [`enter`]
+unsafe fn write_file(path: &CStr, content: &[u8]) -> io::Result<()> {
+    let fd = check(libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC))?;
+    let written = libc::write(fd, content.as_ptr().cast(), content.len());
+    libc::close(fd);
+    if written != content.len() as isize {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Exits with the same wait status as a process that exited with `status`
+///
+/// # Safety
+///
+/// Same as [`enter`]
+unsafe fn exit_like(status: libc::c_int) -> ! {
+    if libc::WIFEXITED(status) {
+        libc::_exit(libc::WEXITSTATUS(status));
+    }
+    let signal = if libc::WIFSIGNALED(status) {
+        libc::WTERMSIG(status)
+    } else {
+        libc::SIGKILL
+    };
+    libc::signal(signal, libc::SIG_DFL);
+    let mut set = std::mem::zeroed();
+    libc::sigemptyset(&mut set);
+    libc::sigaddset(&mut set, signal);
+    libc::sigprocmask(libc::SIG_UNBLOCK, &set, std::ptr::null_mut());
+    libc::kill(libc::getpid(), signal);
+    libc::_exit(128 + signal);
+}
+
+/// Kills the process on the system calls in [`FORBIDDEN_SYSCALLS`], on `clone` with
+/// [`NAMESPACE_FLAGS`] and on system calls of other architectures. `clone3` fails instead,
+/// its flags can't be checked but the C library falls back to `clone`.
+fn seccomp_filter() -> Vec<libc::sock_filter> {
+    let statement = |code: u32, k: u32| libc::sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    };
+    let jump = |code: u32, k: u32, jt: u8, jf: u8| libc::sock_filter {
+        code: (libc::BPF_JMP | code | libc::BPF_K) as u16,
+        jt,
+        jf,
+        k,
+    };
+    let load = |offset: usize| statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset as u32);
+    let kill = statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS);
+    let allow = statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW);
+    // Offsets in `seccomp_data`, the arguments are little-endian 64 bit numbers
+    let (nr, arch, first_argument) = (0, 4, 16);
+
+    let mut filter = vec![load(arch), jump(libc::BPF_JEQ, AUDIT_ARCH, 1, 0), kill];
+    filter.push(load(nr));
+    // x32 system calls on x86_64
+    filter.extend([jump(libc::BPF_JGE, 0x4000_0000, 0, 1), kill]);
+    for &syscall in FORBIDDEN_SYSCALLS {
+        filter.extend([jump(libc::BPF_JEQ, syscall as u32, 0, 1), kill]);
+    }
+    filter.extend([
+        jump(libc::BPF_JEQ, libc::SYS_clone3 as u32, 0, 1),
+        statement(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32,
+        ),
+    ]);
+    filter.extend([
+        jump(libc::BPF_JEQ, libc::SYS_clone as u32, 0, 3),
+        load(first_argument),
+        jump(libc::BPF_JSET, NAMESPACE_FLAGS as u32, 0, 1),
+        kill,
+        allow,
+    ]);
+    filter
+}
+
+#[test]
+fn sandbox() {
+    use std::fs;
+
+    // Set when the test runs itself in the sandbox, see below
+    const SOCKET_ENV: &str = "CHINT_SANDBOX_TEST_SOCKET";
+    if std::env::var_os(SOCKET_ENV).is_some() {
+        // SAFETY: socket has no preconditions
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+        return;
+    }
+
+    let run = |command: &mut Command| {
+        apply(command, &[]).unwrap();
+        command.status()
+    };
+    let sh = |script: &str| {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    };
+
+    let status = match run(&mut sh(
+        "echo hi > /tmp/file && test \"$(cat /tmp/file)\" = hi",
+    )) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            eprintln!("Skipped, unprivileged user namespaces are not allowed here: {e}");
+            return;
+        }
+        status => status.unwrap(),
+    };
+    assert!(status.success());
+    assert!(!run(&mut sh("touch \"$HOME/.chint-sandbox-test\""))
+        .unwrap()
+        .success());
+
+    // A solution outside the working directory, in a directory of `/tmp` or right in it
+    let dir = tempfile::tempdir_in("/tmp").unwrap();
+    let in_dir = dir.path().join("solution.sh");
+    fs::write(&in_dir, "echo hi").unwrap();
+    let in_tmp = tempfile::NamedTempFile::new_in("/tmp").unwrap();
+    fs::write(&in_tmp, "echo hi").unwrap();
+    for file in [in_dir, in_tmp.path().to_path_buf()] {
+        let mut command = Command::new("sh");
+        command.arg(&file);
+        apply(&mut command, &[file]).unwrap();
+        assert!(command.status().unwrap().success());
+    }
+    let mut command = Command::new("sh");
+    command.arg(in_tmp.path());
+    assert!(!run(&mut command).unwrap().success());
+
+    // The binary itself might be hidden by the new `/tmp`, the link still leads to it
+    let mut socket = Command::new("/proc/self/exe");
+    socket.args(["--exact", "sandbox::sandbox", "--test-threads=1"]);
+    socket.env(SOCKET_ENV, "1");
+    let status = run(&mut socket).unwrap();
+    assert_eq!(violation(status), Some("Used a forbidden system call"));
+}
@@ -0,0 +1,68 @@
+//! `chint stats`, a summary of the progress
+use chrono::Local;
+use macro_types::{Difficulty, Problem};
+
+use crate::progress::{self, Progress};
+
+pub fn stats() -> anyhow::Result<()> {
+    let progress = Progress::load()?;
+    let problems: Vec<(&Problem, _)> = crate::problems()
+        .iter()
+        .map(|problem| (problem, progress.get(&problem.slug).unwrap_or_default()))
+        .collect();
+
+    let solved = problems.iter().filter(|(_, p)| p.solved).count();
+    println!(
+        "Solved: {solved}/{} problems{}",
+        problems.len(),
+        percentage(solved, problems.len())
+    );
+    let difficulties = [
+        Some(Difficulty::Easy),
+        Some(Difficulty::Medium),
+        Some(Difficulty::Hard),
+        None,
+    ];
+    for difficulty in difficulties {
+        let (total, solved) = problems
+            .iter()
+            .filter(|(problem, _)| problem.difficulty == difficulty)
+            .fold((0, 0), |(total, solved), (_, p)| {
+                (total + 1, solved + usize::from(p.solved))
+            });
+        if total > 0 {
+            let name = difficulty.map_or("unknown".to_string(), |d| d.to_string());
+            println!("  {name}: {solved}/{total}");
+        }
+    }
+
+    let attempted = problems
+        .iter()
+        .filter(|(_, p)| p.attempts > 0 && !p.solved)
+        .count();
+    println!("Attempted, not solved yet: {attempted}");
+    let first_try = problems
+        .iter()
+        .filter(|(_, p)| p.attempts_to_solve == Some(1))
+        .count();
+    println!("Solved on the first try: {first_try}/{solved}");
+
+    let runs = progress.runs.len();
+    let passed = progress.runs.iter().filter(|run| run.solved).count();
+    println!(
+        "Runs: {runs}, all tests passed in {passed}{}",
+        percentage(passed, runs)
+    );
+
+    let (longest, current) = progress::streaks(&progress.active_days(), Local::now().date_naive());
+    println!("Current streak: {current} days");
+    println!("Longest streak: {longest} days");
+    Ok(())
+}
+
+fn percentage(part: usize, total: usize) -> String {
+    if total == 0 {
+        return String::new();
+    }
+    format!(" ({:.0}%)", part as f64 * 100.0 / total as f64)
+}
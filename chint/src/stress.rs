@@ -0,0 +1,208 @@
+//! `chint stress`, looking for an input on which a solution differs from a reference solution
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use macro_types::{Problem, Program, Test};
+
+use crate::process::Limits;
+use crate::program::PreparedProgram;
+use crate::test_runner::{self, Judge, TestOptions};
+
+pub struct StressOptions {
+    /// Number of inputs to try
+    pub iterations: u64,
+    /// Seed of the first input, the next ones count up from it
+    pub seed: u64,
+    /// Limits of the solution
+    pub test: TestOptions,
+    /// Wall-clock time the generator and the reference solution can take, a brute force
+    /// reference is often much slower than the solution
+    pub reference_timeout: Duration,
+    /// Where the failing input is saved
+    pub save_dir: PathBuf,
+}
+
+/// Runs the solution and the reference solution on inputs printed by `<generator> <seed>`
+/// until the solution fails, judged like the tests of `problem` with the output of the
+/// reference solution as the expected output. The input and expected output of the failure
+/// are saved to `stress-<seed>.in` and `stress-<seed>.out` in [`StressOptions::save_dir`],
+/// its result is returned.
+///
+/// The problem's own generator and reference solution are used if they aren't given.
+pub fn stress(
+    problem: &Problem,
+    generator: Option<&Path>,
+    reference: Option<&Path>,
+    solution: &Path,
+    options: &StressOptions,
+) -> anyhow::Result<Option<test_runner::Result>> {
+    if problem.interactor.is_some() {
+        bail!("Interactive problems can't be stress tested");
+    }
+    // Kept until the end, the programs are in their directories
+    let generator = prepare(generator, problem.generator.as_ref(), "generator", "--gen")?;
+    let reference = prepare(
+        reference,
+        problem.reference.as_ref(),
+        "reference solution",
+        "--ref",
+    )?;
+    let solution = prepare(Some(solution), None, "solution", "--sol")?;
+    let test_options = TestOptions {
+        visible: vec![solution.path.clone()],
+        ..options.test.clone()
+    };
+    let judge = Judge::new(problem)?;
+    let show_progress = io::stdout().is_terminal();
+
+    for i in 0..options.iterations {
+        let seed = options.seed.wrapping_add(i);
+        if show_progress {
+            print!("\r=== [{}/{}] seed {seed}", i + 1, options.iterations);
+            io::stdout().flush()?;
+        }
+        let mut test = Test {
+            test_name: Cow::Owned(format!("stress-{seed}")),
+            input: Cow::Borrowed(b""),
+            output: Cow::Borrowed(b""),
+            time_limit: None,
+        };
+        let limits = test_options.limits(problem, &test);
+        let helper_limits = Limits {
+            wall_time: options.reference_timeout,
+            ..test_runner::helper_limits(&limits)
+        };
+
+        let command = format!("{} {seed}", generator.command);
+        let generated = test_runner::run_program(&test, &command, &helper_limits)?;
+        if !generated.status.is_success() {
+            bail!(
+                "Generator failed on seed {seed}: {}\n{}",
+                generated.status.verdict(),
+                generated.stderr
+            );
+        }
+        test.input = Cow::Owned(generated.stdout.into_bytes());
+
+        let expected = test_runner::run_program(&test, &reference.command, &helper_limits)?;
+        if !expected.status.is_success() {
+            bail!(
+                "Reference solution failed on seed {seed}: {}\n{}",
+                expected.status.verdict(),
+                expected.stderr
+            );
+        }
+        test.output = Cow::Owned(expected.stdout.into_bytes());
+
+        let result = test_runner::run_test(&test, &solution.command, &limits, &judge)?;
+        if result.status.is_success() {
+            continue;
+        }
+
+        if show_progress {
+            println!();
+        }
+        test_runner::print_test(i as usize + 1, options.iterations as usize, &test, &result)?;
+        for (extension, content) in [("in", &test.input), ("out", &test.output)] {
+            let path = options
+                .save_dir
+                .join(format!("{}.{extension}", test.test_name));
+            fs::write(&path, content.as_ref())
+                .with_context(|| format!("Unable to write {}", path.display()))?;
+        }
+        let path = options.save_dir.join(test.test_name.as_ref());
+        println!(
+            "Seed {seed} failed, saved its input to {0}.in and the expected output to {0}.out",
+            path.display()
+        );
+        return Ok(Some(result));
+    }
+    if show_progress {
+        println!();
+    }
+    println!("No difference found in {} inputs", options.iterations);
+    Ok(None)
+}
+
+/// Prepares the program in `file`, or the problem's own `bundled` one if no file is given
+fn prepare(
+    file: Option<&Path>,
+    bundled: Option<&Program>,
+    what: &str,
+    flag: &str,
+) -> anyhow::Result<PreparedProgram> {
+    let read;
+    let program = match file {
+        Some(file) => {
+            read = Program {
+                file_name: file
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .with_context(|| format!("Invalid file name {}", file.display()))?
+                    .into(),
+                source: fs::read(file)
+                    .with_context(|| format!("Unable to read {}", file.display()))?
+                    .into(),
+            };
+            &read
+        }
+        None => {
+            bundled.with_context(|| format!("The problem has no {what}, give one with {flag}"))?
+        }
+    };
+    PreparedProgram::new(program).with_context(|| format!("Unable to prepare the {what}"))
+}
+
+#[test]
+fn stress_finds_failure() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let script = |name: &str, source: &str| {
+        let path = dir.path().join(name);
+        fs::write(&path, source).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    };
+    let generator = script("gen", "#!/bin/sh\necho $1\n");
+    let reference = script("ref", "#!/bin/sh\nread n\necho $((n * 2))\n");
+    let solution = script(
+        "sol",
+        "#!/bin/sh\nread n\n[ $n -eq 3 ] && echo 0 || echo $((n * 2))\n",
+    );
+    let problem = test_runner::test_problem_with(vec![]);
+    let options = StressOptions {
+        iterations: 10,
+        seed: 1,
+        test: TestOptions::default(),
+        reference_timeout: Duration::from_secs(10),
+        save_dir: dir.path().to_path_buf(),
+    };
+
+    let failed = stress(
+        &problem,
+        Some(&generator),
+        Some(&reference),
+        &solution,
+        &options,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(failed.test_name, "stress-3");
+    assert!(!failed.status.is_success());
+    assert_eq!(fs::read(dir.path().join("stress-3.in")).unwrap(), b"3\n");
+    assert_eq!(fs::read(dir.path().join("stress-3.out")).unwrap(), b"6\n");
+
+    let passed = stress(
+        &problem,
+        Some(&generator),
+        Some(&reference),
+        &reference,
+        &options,
+    );
+    assert!(passed.unwrap().is_none());
+}
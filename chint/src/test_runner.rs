@@ -1,150 +1,1060 @@
-use anyhow::Context;
-use macro_types::{Problem, Test};
+use anyhow::{bail, Context};
+use macro_types::{CompareMode, Problem, Test};
+use serde::Serialize;
 use shlex::split;
-use std::io::{BufReader, Read};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
 use std::time::Duration;
-use std::{
-    io::Write,
-    process::{Command, Stdio},
-};
-use wait_timeout::ChildExt;
 
-use crate::text_diff;
+use crate::compare::{self, Mismatch};
+use crate::process::{self, Limits, Termination, Usage};
+use crate::program::PreparedProgram;
+#[cfg(target_os = "linux")]
+use crate::sandbox;
+use crate::{interactive, report, text_diff};
 
-enum ResultStatus {
+#[derive(Serialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum ResultStatus {
     Success,
-    IncorrectExitCode { exit_code: i32 },
-    IncorrectOutput,
+    IncorrectExitCode {
+        exit_code: i32,
+    },
+    /// The solution was killed by a signal, usually because it crashed
+    RuntimeError {
+        signal: i32,
+    },
+    IncorrectOutput {
+        mismatch: Mismatch,
+    },
+    /// Output was rejected by the problem's checker
+    CheckerRejected {
+        message: String,
+    },
     Timeout,
+    MemoryLimitExceeded,
+    /// The solution wrote more than [`TestOptions::output_limit`] to stdout
+    OutputLimitExceeded,
+    /// The sandbox stopped the solution from doing something it isn't allowed to
+    SecurityViolation {
+        reason: String,
+    },
+    /// The solution couldn't be compiled, so no tests were run
+    CompilationError,
 }
-struct Result {
-    time_taken: Duration,
-    stdout: String,
-    stderr: String,
-    status: ResultStatus,
+
+impl ResultStatus {
+    pub fn is_success(&self) -> bool {
+        matches!(self, ResultStatus::Success)
+    }
+
+    /// Short name of the status, e.g. for tables
+    pub fn verdict(&self) -> &'static str {
+        match self {
+            ResultStatus::Success => "Success",
+            ResultStatus::IncorrectExitCode { .. } => "Incorrect Exit Code",
+            ResultStatus::RuntimeError { .. } => "Runtime Error",
+            ResultStatus::IncorrectOutput { .. } => "Incorrect Output",
+            ResultStatus::CheckerRejected { .. } => "Wrong Answer",
+            ResultStatus::Timeout => "Timed Out",
+            ResultStatus::MemoryLimitExceeded => "Memory Limit Exceeded",
+            ResultStatus::CompilationError => "Compilation Error",
+            ResultStatus::SecurityViolation { .. } => "Security Violation",
+            ResultStatus::OutputLimitExceeded => "Output Limit Exceeded",
+        }
+    }
+
+    /// Exit code of chint if this is the status of the first failed test, see
+    /// `cli::EXIT_CODES_HELP`
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ResultStatus::Success => 0,
+            ResultStatus::IncorrectOutput { .. } | ResultStatus::CheckerRejected { .. } => 3,
+            ResultStatus::IncorrectExitCode { .. } | ResultStatus::RuntimeError { .. } => 4,
+            ResultStatus::Timeout => 5,
+            ResultStatus::MemoryLimitExceeded => 6,
+            ResultStatus::CompilationError => 7,
+            ResultStatus::SecurityViolation { .. } => 8,
+            ResultStatus::OutputLimitExceeded => 9,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Result {
+    pub test_name: String,
+    #[serde(serialize_with = "report::serialize_secs")]
+    pub time_taken: Duration,
+    /// User + system time
+    #[serde(serialize_with = "report::serialize_secs")]
+    pub cpu_time: Duration,
+    /// In bytes
+    pub peak_memory: u64,
+    /// `None` if the solution was killed
+    pub exit_code: Option<i32>,
+    #[serde(serialize_with = "report::serialize_truncated")]
+    pub stdout: String,
+    #[serde(serialize_with = "report::serialize_truncated")]
+    pub stderr: String,
+    /// What the solution and the interactor said to each other, empty if the problem isn't
+    /// interactive
+    #[serde(
+        skip_serializing_if = "String::is_empty",
+        serialize_with = "report::serialize_truncated"
+    )]
+    pub transcript: String,
+    #[serde(flatten)]
+    pub status: ResultStatus,
+}
+
+/// Decides if the output of a solution is correct
+pub enum Judge<'a> {
+    Checker(PreparedProgram),
+    /// Talks with the solution while it runs, see [`run_interactive_test`]
+    Interactor(PreparedProgram),
+    Compare(&'a CompareMode),
 }
 
-fn run_test(test: &Test, command: &str, timeout: Duration) -> anyhow::Result<Result> {
+impl<'a> Judge<'a> {
+    pub fn new(problem: &'a Problem) -> anyhow::Result<Self> {
+        Ok(match (&problem.checker, &problem.interactor) {
+            (Some(checker), _) => {
+                Judge::Checker(PreparedProgram::new(checker).context("Unable to prepare checker")?)
+            }
+            (None, Some(interactor)) => Judge::Interactor(
+                PreparedProgram::new(interactor).context("Unable to prepare interactor")?,
+            ),
+            (None, None) => Judge::Compare(&problem.compare),
+        })
+    }
+
+    fn judge(&self, test: &Test, output: &str, limits: &Limits) -> anyhow::Result<ResultStatus> {
+        match self {
+            Judge::Checker(checker) => run_checker(checker, test, output, limits),
+            Judge::Interactor(_) => unreachable!("interactive tests are judged while they run"),
+            Judge::Compare(mode) => {
+                match compare::compare(mode, output, &String::from_utf8_lossy(&test.output)) {
+                    None => Ok(ResultStatus::Success),
+                    Some(mismatch) => Ok(ResultStatus::IncorrectOutput { mismatch }),
+                }
+            }
+        }
+    }
+}
+
+/// Runs the checker on `output`, returns [`ResultStatus::Success`] if it was accepted. It
+/// gets the wall-clock time limit of the solution.
+fn run_checker(
+    checker: &PreparedProgram,
+    test: &Test,
+    output: &str,
+    limits: &Limits,
+) -> anyhow::Result<ResultStatus> {
+    // Named after the test, tests can be judged in parallel
+    let path = |name: &str| {
+        checker
+            .dir
+            .path()
+            .join(format!("{}.{name}", test.test_name))
+    };
+    let input_path = path("input");
+    let output_path = path("output");
+    let answer_path = path("answer");
+    fs::write(&input_path, &test.input)?;
+    fs::write(&output_path, output)?;
+    fs::write(&answer_path, &test.output)?;
+
+    let mut rust_command = piped(&checker.command).context("Invalid checker command")?;
+    rust_command.args([&input_path, &output_path, &answer_path]);
+    let (termination, _, checker_output) =
+        process::run(&mut rust_command, b"", &helper_limits(limits))
+            .context("Unable to run the checker")?;
+    let message = String::from_utf8_lossy(&checker_output.stderr)
+        .trim_end()
+        .to_string();
+
+    let exit_status = match termination {
+        Termination::Exited(e) => e,
+        Termination::Cancelled => return Err(Cancelled.into()),
+        Termination::TimedOut => bail!("Checker timed out"),
+        _ => bail!("Checker went over its limits"),
+    };
+    match exit_status.code() {
+        Some(0) => Ok(ResultStatus::Success),
+        Some(1) | Some(2) => Ok(ResultStatus::CheckerRejected { message }),
+        _ => bail!("Checker failed ({}): {}", exit_status, message),
+    }
+}
+
+/// `command` split like a shell would, with its stdio piped
+fn piped(command: &str) -> anyhow::Result<Command> {
     let a = split(command).context("Invalid Command")?;
 
-    let mut rust_command = Command::new(a.get(0).context("Empty Command")?);
+    let mut rust_command = Command::new(a.first().context("Empty Command")?);
     rust_command.args(&a[1..]);
     rust_command.stdin(Stdio::piped());
     rust_command.stdout(Stdio::piped());
     rust_command.stderr(Stdio::piped());
-    let mut child = rust_command.spawn()?;
-    child.stdin.take().unwrap().write_all(test.input)?;
-    let start = std::time::Instant::now();
-    let r = child.wait_timeout(timeout)?;
-    if r.is_none() {
-        child.kill()?;
-    }
-    let end = std::time::Instant::now();
-    let duration = end - start;
-
-    let mut stdout = child.stdout.take().unwrap();
-    let mut stderr = child.stderr.take().unwrap();
-
-    // stdout
-    let mut reader = BufReader::new(&mut stdout);
-    let mut output = vec![];
-
-    reader.read_to_end(&mut output)?;
-
-    // stderr
-    let mut reader = BufReader::new(&mut stderr);
-    let mut error = vec![];
-    reader.read_to_end(&mut error)?;
-
-    let error = String::from_utf8_lossy(&error).to_string();
-    let output = String::from_utf8_lossy(&output).to_string();
-
-    let exit_status = match r {
-        Some(e) => e,
-        None => {
-            return Ok(Result {
-                time_taken: duration,
-                stdout: output,
-                stderr: error,
-                status: ResultStatus::Timeout,
-            })
-        }
-    };
-
-    if !exit_status.success() {
-        return Ok(Result {
-            time_taken: duration,
-            stderr: error,
-            stdout: output,
-            status: ResultStatus::IncorrectExitCode {
-                exit_code: exit_status.code().context("Exited without exit code")?,
+    Ok(rust_command)
+}
+
+/// Limits of a trusted program of the problem, like the checker, run for a test with
+/// `limits`. It only needs to be stopped in case it gets stuck.
+pub fn helper_limits(limits: &Limits) -> Limits {
+    Limits {
+        output: limits.output,
+        cancel: limits.cancel.clone(),
+        ..Limits::with_wall_time(limits.wall_time)
+    }
+}
+
+/// The command that runs the solution with `limits`, its stdio is piped
+fn solution_command(command: &str, limits: &Limits) -> anyhow::Result<Command> {
+    let mut rust_command = piped(command)?;
+    process::apply_limits(&mut rust_command, limits);
+    if limits.sandbox {
+        #[cfg(target_os = "linux")]
+        let applied = sandbox::apply(&mut rust_command, &limits.visible);
+        #[cfg(not(target_os = "linux"))]
+        let applied: std::io::Result<()> = Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "The sandbox is only supported on Linux",
+        ));
+        applied.context("Unable to set up the sandbox")?;
+    }
+    Ok(rust_command)
+}
+
+fn run_error_context(limits: &Limits) -> &'static str {
+    match limits.sandbox {
+        true => "Unable to run the solution in the sandbox, it needs unprivileged user namespaces",
+        false => "Unable to run the solution",
+    }
+}
+
+/// Status of a solution that didn't exit with 0, `None` if it did
+fn failure_status(
+    termination: &Termination,
+    usage: &Usage,
+    stderr: &str,
+    limits: &Limits,
+) -> anyhow::Result<Option<ResultStatus>> {
+    let exit_status = match termination {
+        Termination::Exited(e) => *e,
+        Termination::TimedOut => return Ok(Some(ResultStatus::Timeout)),
+        Termination::MemoryLimitExceeded => return Ok(Some(ResultStatus::MemoryLimitExceeded)),
+        Termination::OutputLimitExceeded => return Ok(Some(ResultStatus::OutputLimitExceeded)),
+        Termination::Cancelled => return Err(Cancelled.into()),
+    };
+
+    #[cfg(target_os = "linux")]
+    if let Some(reason) = limits
+        .sandbox
+        .then(|| sandbox::violation(exit_status))
+        .flatten()
+    {
+        return Ok(Some(ResultStatus::SecurityViolation {
+            reason: reason.to_string(),
+        }));
+    }
+
+    // Allocations over the data limit of `process::apply_limits` fail in the solution before
+    // `process::wait` sees the memory in use, e.g. a single huge one
+    if let Some(limit) = limits.memory {
+        let near_limit = usage.peak_memory >= limit / 10 * 9;
+        let out_of_memory = OUT_OF_MEMORY_MESSAGES.iter().any(|m| stderr.contains(m));
+        if !exit_status.success() && (near_limit || out_of_memory) {
+            return Ok(Some(ResultStatus::MemoryLimitExceeded));
+        }
+    }
+
+    if let Some(signal) = exit_status.signal() {
+        return Ok(Some(ResultStatus::RuntimeError { signal }));
+    }
+    Ok(exit_status
+        .code()
+        .filter(|&code| code != 0)
+        .map(|exit_code| ResultStatus::IncorrectExitCode { exit_code }))
+}
+
+fn exit_code(termination: &Termination) -> Option<i32> {
+    match termination {
+        Termination::Exited(e) => e.code(),
+        _ => None,
+    }
+}
+
+/// Runs `command` on the input of `test` without judging its output, the status only tells
+/// if it exited successfully
+pub fn run_program(test: &Test, command: &str, limits: &Limits) -> anyhow::Result<Result> {
+    let mut rust_command = solution_command(command, limits)?;
+    let (termination, usage, output) = process::run(&mut rust_command, &test.input, limits)
+        .with_context(|| run_error_context(limits))?;
+    let error = String::from_utf8_lossy(&output.stderr).to_string();
+    let output = String::from_utf8_lossy(&output.stdout).to_string();
+
+    let mut result = Result {
+        test_name: test.test_name.to_string(),
+        time_taken: usage.wall_time,
+        cpu_time: usage.cpu_time,
+        peak_memory: usage.peak_memory,
+        exit_code: exit_code(&termination),
+        stdout: output,
+        stderr: error,
+        transcript: String::new(),
+        status: ResultStatus::Success,
+    };
+    if let Some(status) = failure_status(&termination, &usage, &result.stderr, limits)? {
+        result.status = status;
+    }
+    Ok(result)
+}
+
+pub fn run_test(
+    test: &Test,
+    command: &str,
+    limits: &Limits,
+    judge: &Judge,
+) -> anyhow::Result<Result> {
+    if let Judge::Interactor(interactor) = judge {
+        return run_interactive_test(test, command, limits, interactor);
+    }
+    let mut result = run_program(test, command, limits)?;
+    if result.status.is_success() {
+        result.status = judge.judge(test, &result.stdout, limits)?;
+    }
+    Ok(result)
+}
+
+/// Runs the solution connected to the interactor, which is run as
+/// `interactor <input> <answer>` and decides the verdict with its exit code like a checker
+fn run_interactive_test(
+    test: &Test,
+    command: &str,
+    limits: &Limits,
+    interactor: &PreparedProgram,
+) -> anyhow::Result<Result> {
+    // Named after the test, tests can be run in parallel
+    let path = |name: &str| {
+        interactor
+            .dir
+            .path()
+            .join(format!("{}.{name}", test.test_name))
+    };
+    let input_path = path("input");
+    let answer_path = path("answer");
+    fs::write(&input_path, &test.input)?;
+    fs::write(&answer_path, &test.output)?;
+
+    let mut interactor_command =
+        piped(&interactor.command).context("Invalid interactor command")?;
+    interactor_command.args([&input_path, &answer_path]);
+    let interactor_limits = Limits {
+        // It waits for the solution, which is killed at its own time limit
+        wall_time: limits.wall_time.saturating_mul(2),
+        ..helper_limits(limits)
+    };
+
+    let mut solution_command = solution_command(command, limits)?;
+    let interaction = interactive::run(
+        &mut solution_command,
+        &mut interactor_command,
+        limits,
+        &interactor_limits,
+    )
+    .with_context(|| run_error_context(limits))?;
+    let (termination, usage) = interaction.solution;
+
+    let mut result = Result {
+        test_name: test.test_name.to_string(),
+        time_taken: usage.wall_time,
+        cpu_time: usage.cpu_time,
+        peak_memory: usage.peak_memory,
+        exit_code: exit_code(&termination),
+        stdout: String::new(),
+        stderr: String::from_utf8_lossy(&interaction.solution_stderr).to_string(),
+        transcript: interaction.transcript,
+        status: ResultStatus::Success,
+    };
+    // Going over a limit is the solution's fault whatever the interactor thinks, a crash is
+    // often caused by an answer the interactor rejects
+    let failure = failure_status(&termination, &usage, &result.stderr, limits)?;
+    if let Some(
+        status @ (ResultStatus::Timeout
+        | ResultStatus::MemoryLimitExceeded
+        | ResultStatus::OutputLimitExceeded
+        | ResultStatus::SecurityViolation { .. }),
+    ) = failure
+    {
+        result.status = status;
+        return Ok(result);
+    }
+
+    let message = String::from_utf8_lossy(&interaction.interactor_stderr)
+        .trim_end()
+        .to_string();
+    let exit_status = match interaction.interactor.0 {
+        Termination::Exited(e) => e,
+        Termination::Cancelled => return Err(Cancelled.into()),
+        Termination::TimedOut => bail!("Interactor timed out"),
+        _ => bail!("Interactor went over its limits"),
+    };
+    result.status = match exit_status.code() {
+        Some(0) => failure.unwrap_or(ResultStatus::Success),
+        Some(1) | Some(2) => ResultStatus::CheckerRejected { message },
+        _ => bail!("Interactor failed ({}): {}", exit_status, message),
+    };
+    Ok(result)
+}
+
+/// Name and explanation of a signal that killed a solution
+pub fn describe_signal(signal: i32) -> (&'static str, &'static str) {
+    match signal {
+        libc::SIGSEGV => (
+            "SIGSEGV",
+            "Segmentation fault, an invalid memory access like an index out of bounds, a null \
+             pointer or a stack overflow from too deep recursion",
+        ),
+        libc::SIGBUS => ("SIGBUS", "Bus error, an invalid memory access"),
+        libc::SIGFPE => (
+            "SIGFPE",
+            "Arithmetic error, usually an integer division by zero",
+        ),
+        libc::SIGABRT => (
+            "SIGABRT",
+            "Aborted, e.g. by a failed assertion, an uncaught C++ exception or memory corruption \
+             found by the allocator",
+        ),
+        libc::SIGKILL => (
+            "SIGKILL",
+            "Killed, usually because the system ran out of memory",
+        ),
+        libc::SIGILL => (
+            "SIGILL",
+            "Illegal instruction, e.g. after undefined behaviour",
+        ),
+        libc::SIGPIPE => ("SIGPIPE", "Wrote to a pipe that was closed"),
+        libc::SIGXCPU => ("SIGXCPU", "Went over the CPU time limit"),
+        libc::SIGTERM => ("SIGTERM", "Terminated"),
+        _ => ("an unknown signal", "Killed by a signal"),
+    }
+}
+
+/// The tests were stopped with [`TestOptions::cancel`]
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl Error for Cancelled {}
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+/// What the runtimes of the languages print when an allocation fails
+const OUT_OF_MEMORY_MESSAGES: &[&str] = &[
+    // Rust
+    "memory allocation of",
+    // C++
+    "std::bad_alloc",
+    // Python and Ruby
+    "MemoryError",
+    // Java
+    "java.lang.OutOfMemoryError",
+    // Go and JavaScript
+    "out of memory",
+];
+
+/// Used when neither the problem nor the options have a time limit
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often [`TestOptions::cancel`] is checked while waiting for results
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Clone)]
+pub struct TestOptions {
+    /// Overrides the time limits of the problem
+    pub timeout: Option<Duration>,
+    /// Time limits are multiplied by this, e.g. for slow languages
+    pub time_multiplier: f64,
+    /// Apply the timeout to CPU time instead of wall-clock time
+    pub cpu_time: bool,
+    /// In megabytes, overrides the problem's memory limit
+    pub memory_limit: Option<u64>,
+    /// In megabytes
+    pub output_limit: u64,
+    /// Run all the tests instead of stopping at the first failure
+    pub keep_going: bool,
+    /// Number of tests to run in parallel
+    pub jobs: usize,
+    /// Pin each parallel test to a CPU
+    pub pin_cpus: bool,
+    /// Run the solution in the [`sandbox`]
+    pub sandbox: bool,
+    /// Files the solution needs that the sandbox might hide, like its source
+    pub visible: Vec<PathBuf>,
+    /// Stops the tests when set, the running solution is killed
+    pub cancel: Arc<AtomicBool>,
+}
+
+/// The defaults of `chint test`
+impl Default for TestOptions {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            time_multiplier: 1.0,
+            cpu_time: false,
+            memory_limit: None,
+            output_limit: 64,
+            keep_going: false,
+            jobs: 1,
+            pin_cpus: false,
+            sandbox: false,
+            visible: vec![],
+            cancel: Arc::default(),
+        }
+    }
+}
+
+impl TestOptions {
+    pub fn limits(&self, problem: &Problem, test: &Test) -> Limits {
+        let timeout = self
+            .timeout
+            .or(test.time_limit)
+            .or(problem.time_limit)
+            .unwrap_or(DEFAULT_TIMEOUT);
+        let timeout = Duration::try_from_secs_f64(timeout.as_secs_f64() * self.time_multiplier)
+            .unwrap_or(Duration::MAX);
+        Limits {
+            // Safety net for solutions that wait without using CPU, e.g. for input
+            wall_time: if self.cpu_time {
+                timeout.saturating_mul(2)
+            } else {
+                timeout
             },
-        });
+            cpu_time: self.cpu_time.then_some(timeout),
+            memory: self
+                .memory_limit
+                .or(problem.memory_limit)
+                .map(|megabytes| megabytes.saturating_mul(1024 * 1024)),
+            output: Some(self.output_limit.saturating_mul(1024 * 1024)),
+            cancel: self.cancel.clone(),
+            cpu: None,
+            sandbox: self.sandbox,
+            visible: self.visible.clone(),
+        }
     }
+}
 
-    // TODO: stricter match
-    if output.trim_end() == String::from_utf8_lossy(test.output).trim_end() {
-        Ok(Result {
-            time_taken: duration,
-            stderr: error,
-            stdout: output,
-            status: ResultStatus::Success,
-        })
+/// Runs the tests and prints their results, returns the results of the tests that were run.
+///
+/// Up to [`TestOptions::jobs`] tests run at the same time, their results are printed in the
+/// order of the tests anyway. Once a test fails the tests after it are killed, unless
+/// [`TestOptions::keep_going`] is set.
+pub fn test_problem(
+    problem: &Problem,
+    command: &str,
+    options: &TestOptions,
+) -> anyhow::Result<Vec<Result>> {
+    let judge = Judge::new(problem)?;
+    let tests = &problem.tests;
+    let jobs = options.jobs.clamp(1, tests.len().max(1));
+    let cpus = if options.pin_cpus {
+        if cfg!(not(target_os = "linux")) {
+            eprintln!("Warning: --pin-cpus is only supported on Linux, the tests are not pinned");
+        }
+        let cpus = process::available_cpus().context("Unable to get the available CPUs")?;
+        if jobs > cpus.len() {
+            eprintln!(
+                "Warning: running {jobs} tests at a time on {} CPUs, timing will be less accurate",
+                cpus.len()
+            );
+        }
+        cpus
     } else {
-        Ok(Result {
-            time_taken: duration,
-            stderr: error,
-            stdout: output,
-            status: ResultStatus::IncorrectOutput,
-        })
+        vec![]
+    };
+
+    // Index of the next test to start
+    let next = AtomicUsize::new(0);
+    // Tests after this one are not started, lowered to the first failed test
+    let last = AtomicUsize::new(usize::MAX);
+    // Kills the solution of each test separately
+    let cancels: Vec<Arc<AtomicBool>> = tests.iter().map(|_| Arc::default()).collect();
+    let cancel_from = |first: usize| {
+        for cancel in &cancels[first..] {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    };
+    let stop = || {
+        last.store(0, Ordering::Relaxed);
+        cancel_from(0);
+    };
+
+    let results = thread::scope(|scope| -> anyhow::Result<Vec<Result>> {
+        let (sender, receiver) = mpsc::channel();
+        for worker in 0..jobs {
+            let sender = sender.clone();
+            let (next, last, cancels, cpus, judge) = (&next, &last, &cancels, &cpus, &judge);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= tests.len() || i > last.load(Ordering::Relaxed) {
+                    break;
+                }
+                let test = &tests[i];
+                let limits = Limits {
+                    cancel: cancels[i].clone(),
+                    cpu: (!cpus.is_empty()).then(|| cpus[worker % cpus.len()]),
+                    ..options.limits(problem, test)
+                };
+                let result = match cancels[i].load(Ordering::Relaxed) {
+                    true => Err(Cancelled.into()),
+                    false => run_test(test, command, &limits, judge),
+                };
+                if sender.send((i, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Results that arrived before the ones of earlier tests
+        let mut pending: Vec<Option<anyhow::Result<Result>>> = tests.iter().map(|_| None).collect();
+        let mut results = vec![];
+        while results.len() < tests.len() {
+            if options.cancel.load(Ordering::Relaxed) {
+                stop();
+                return Err(Cancelled.into());
+            }
+            let (i, result) = match receiver.recv_timeout(CANCEL_POLL_INTERVAL) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let failed = result.as_ref().map_or(true, |r| !r.status.is_success());
+            if failed && !options.keep_going {
+                last.fetch_min(i, Ordering::Relaxed);
+                cancel_from(i + 1);
+            }
+            pending[i] = Some(result);
+
+            while let Some(result) = pending.get_mut(results.len()).and_then(Option::take) {
+                let result = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        stop();
+                        return Err(e);
+                    }
+                };
+                let test = &tests[results.len()];
+                print_test(results.len(), tests.len(), test, &result)?;
+                let success = result.status.is_success();
+                results.push(result);
+                if !success && !options.keep_going {
+                    return Ok(results);
+                }
+            }
+        }
+        Ok(results)
+    })?;
+    if options.keep_going {
+        print_summary(&results);
     }
+    Ok(results)
+}
+
+pub fn print_test(i: usize, count: usize, test: &Test, result: &Result) -> anyhow::Result<()> {
+    println!("=== [{}/{}]", i, count);
+    println!(
+        "=== Time: {:.4}s, CPU: {:.4}s",
+        result.time_taken.as_secs_f64(),
+        result.cpu_time.as_secs_f64()
+    );
+    println!("=== Memory: {}", format_memory(result.peak_memory));
+    print_result(test, result)
 }
 
-/// returns if tests were successful or not
-pub fn test_problem(problem: &Problem, command: &str, timeout: Duration) -> anyhow::Result<bool> {
-    for (i, test) in problem.tests.iter().enumerate() {
-        println!("=== [{}/{}]", i, problem.tests.len());
-        let result = run_test(test, command, timeout)?;
-        println!("=== Time: {:.4}s", result.time_taken.as_secs_f64());
-        match result.status {
-            ResultStatus::Success => {
-                println!("Success")
+/// Prints the compiler's output, returns the result that stands in for the tests
+pub fn compilation_failed(output: String) -> anyhow::Result<Vec<Result>> {
+    let result = Result {
+        test_name: "compilation".to_string(),
+        time_taken: Duration::ZERO,
+        cpu_time: Duration::ZERO,
+        peak_memory: 0,
+        exit_code: None,
+        stdout: String::new(),
+        stderr: output,
+        transcript: String::new(),
+        status: ResultStatus::CompilationError,
+    };
+    let test = Test {
+        test_name: "compilation".into(),
+        input: b"".into(),
+        output: b"".into(),
+        time_limit: None,
+    };
+    print_result(&test, &result)?;
+    Ok(vec![result])
+}
+
+fn print_result(test: &Test, result: &Result) -> anyhow::Result<()> {
+    match &result.status {
+        ResultStatus::CompilationError => {
+            eprintln!("Compilation Error");
+            eprintln!("---------------- Compiler output: ");
+            println!("{}", result.stderr);
+        }
+        ResultStatus::Success => {
+            println!("Success")
+        }
+        ResultStatus::Timeout => {
+            eprintln!("Test Timed out");
+        }
+        ResultStatus::OutputLimitExceeded => {
+            eprintln!("Output Limit Exceeded");
+        }
+        ResultStatus::SecurityViolation { reason } => {
+            eprintln!("Security Violation: {}", reason);
+            if !result.stderr.is_empty() {
+                eprintln!("---------------- Stderr: ");
+                println!("{}", result.stderr);
+            }
+        }
+        ResultStatus::MemoryLimitExceeded => {
+            eprintln!("Memory Limit Exceeded");
+            if !result.stderr.is_empty() {
+                eprintln!("---------------- Stderr: ");
+                println!("{}", result.stderr);
             }
-            ResultStatus::Timeout => {
-                eprintln!("Test Timed out");
-                return Ok(false);
+        }
+        ResultStatus::RuntimeError { signal } => {
+            let (name, explanation) = describe_signal(*signal);
+            eprintln!("Runtime Error: killed by {} (signal {})", name, signal);
+            eprintln!("{}", explanation);
+            if !result.stderr.is_empty() {
+                eprintln!(
+                    "---------------- Stderr (last {} lines): ",
+                    STDERR_TAIL_LINES
+                );
+                println!("{}", tail(&result.stderr, STDERR_TAIL_LINES));
             }
-            ResultStatus::IncorrectExitCode { exit_code } => {
-                eprintln!("Incorrect Exit Code: {}", exit_code);
-                if result.stdout.len() != 0 {
-                    eprintln!("---------------- Stdout: ");
-                    println!("{}", result.stdout);
-                }
-                if result.stderr.len() != 0 {
-                    eprintln!("---------------- Stderr: ");
-                    println!("{}", result.stderr);
-                }
-                return Ok(false);
+        }
+        ResultStatus::IncorrectExitCode { exit_code } => {
+            eprintln!("Incorrect Exit Code: {}", exit_code);
+            if !result.stdout.is_empty() {
+                eprintln!("---------------- Stdout: ");
+                println!("{}", result.stdout);
             }
-            ResultStatus::IncorrectOutput => {
-                println!("Incorrect Output: \nGreen - expected output | Red - Recieved output ");
-                println!("==========================");
-                text_diff::print_diff(
-                    &result.stdout.trim_end(),
-                    &String::from_utf8_lossy(test.output).trim_end(),
-                )?;
-                println!("==========================");
-                // eprintln!("---------------- Expected: ");
-                // println!("{}", String::from_utf8_lossy(test.output));
-                // eprintln!("---------------- Got: ");
-                // println!("{}", result.stdout);
-                if result.stderr.len() != 0 {
-                    eprintln!("---------------- Stderr: ");
-                    println!("{}", result.stderr);
-                }
-                return Ok(false);
+            if !result.stderr.is_empty() {
+                eprintln!("---------------- Stderr: ");
+                println!("{}", result.stderr);
             }
         }
+        ResultStatus::IncorrectOutput { mismatch } => {
+            println!("Incorrect Output: \nGreen - expected output | Red - Recieved output ");
+            println!("==========================");
+            text_diff::print_diff(
+                result.stdout.trim_end(),
+                String::from_utf8_lossy(&test.output).trim_end(),
+                mismatch,
+            )?;
+            println!("==========================");
+            // eprintln!("---------------- Expected: ");
+            // println!("{}", String::from_utf8_lossy(&test.output));
+            // eprintln!("---------------- Got: ");
+            // println!("{}", result.stdout);
+            if !result.stderr.is_empty() {
+                eprintln!("---------------- Stderr: ");
+                println!("{}", result.stderr);
+            }
+        }
+        ResultStatus::CheckerRejected { message } => {
+            println!("Wrong Answer: {}", message);
+            if !result.stdout.is_empty() {
+                eprintln!("---------------- Stdout: ");
+                println!("{}", result.stdout);
+            }
+            if !result.stderr.is_empty() {
+                eprintln!("---------------- Stderr: ");
+                println!("{}", result.stderr);
+            }
+        }
+    }
+    if !result.status.is_success() && !result.transcript.is_empty() {
+        eprintln!("---------------- Transcript (> solution, < interactor): ");
+        println!("{}", result.transcript);
+    }
+    Ok(())
+}
+
+/// Crashes are usually explained at the end of stderr, e.g. by a stack trace
+const STDERR_TAIL_LINES: usize = 20;
+
+/// The last `lines` lines of `text`
+fn tail(text: &str, lines: usize) -> &str {
+    let text = text.trim_end();
+    match text.rmatch_indices('\n').nth(lines.saturating_sub(1)) {
+        Some((i, _)) => &text[i + 1..],
+        None => text,
+    }
+}
+
+fn print_summary(results: &[Result]) {
+    let name_width = results
+        .iter()
+        .map(|r| r.test_name.len())
+        .chain(["Test".len()])
+        .max()
+        .unwrap();
+    println!("=== Summary");
+    println!(
+        "{:name_width$}  {:21}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "Test", "Verdict", "Time", "CPU", "Memory", "Exit Code"
+    );
+    for result in results {
+        let exit_code = match result.exit_code {
+            Some(code) => code.to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:name_width$}  {:21}  {:>8.4}s  {:>8.4}s  {:>9}  {:>9}",
+            result.test_name,
+            result.status.verdict(),
+            result.time_taken.as_secs_f64(),
+            result.cpu_time.as_secs_f64(),
+            format_memory(result.peak_memory),
+            exit_code
+        );
+    }
+    let passed = results.iter().filter(|r| r.status.is_success()).count();
+    println!("Passed {}/{}", passed, results.len());
+}
+
+fn format_memory(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// A problem with only `tests`, for the tests
+#[cfg(test)]
+pub fn test_problem_with(tests: Vec<Test<'static>>) -> Problem<'static> {
+    use std::borrow::Cow;
+
+    Problem {
+        id: 1,
+        slug: "test".into(),
+        title: "Test".into(),
+        description: "".into(),
+        difficulty: None,
+        tags: Cow::Borrowed(&[]),
+        author: None,
+        source: None,
+        tests: Cow::Owned(tests),
+        checker: None,
+        interactor: None,
+        reference: None,
+        generator: None,
+        compare: CompareMode::Lines,
+        time_limit: None,
+        memory_limit: None,
+    }
+}
+
+#[test]
+fn checker_verdict() {
+    use macro_types::Program;
+    use std::borrow::Cow;
+
+    let checker = Judge::Checker(
+        PreparedProgram::new(&Program {
+            file_name: "checker".into(),
+            source: Cow::Borrowed(
+                b"#!/bin/sh\ngrep -q yes \"$2\" || { echo 'expected yes' >&2; exit 1; }\n",
+            ),
+        })
+        .unwrap(),
+    );
+    let test = Test {
+        test_name: "1".into(),
+        input: Cow::Borrowed(b""),
+        output: Cow::Borrowed(b""),
+        time_limit: None,
+    };
+    let limits = Limits::with_wall_time(Duration::from_secs(10));
+
+    let result = run_test(&test, "echo yes", &limits, &checker).unwrap();
+    assert!(matches!(result.status, ResultStatus::Success));
+
+    let result = run_test(&test, "echo no", &limits, &checker).unwrap();
+    assert!(
+        matches!(result.status, ResultStatus::CheckerRejected { message } if message == "expected yes")
+    );
+
+    // More than fits in a pipe
+    let verbose = Judge::Checker(
+        PreparedProgram::new(&Program {
+            file_name: "checker".into(),
+            source: Cow::Borrowed(
+                b"#!/bin/sh\nhead -c 1000000 /dev/zero | tr '\\0' x >&2\nexit 1\n",
+            ),
+        })
+        .unwrap(),
+    );
+    let result = run_test(&test, "echo yes", &limits, &verbose).unwrap();
+    assert!(
+        matches!(result.status, ResultStatus::CheckerRejected { message } if message.len() == 1000000)
+    );
+}
+
+#[test]
+fn parallel_tests() {
+    use std::borrow::Cow;
+    use std::time::Instant;
+
+    let test = |name: &'static str, input: &'static [u8], output: &'static [u8]| Test {
+        test_name: name.into(),
+        input: Cow::Borrowed(input),
+        output: Cow::Borrowed(output),
+        time_limit: None,
+    };
+    let problem = test_problem_with(vec![
+        test("slow", b"0.5", b"0.5"),
+        test("wrong", b"0", b"1"),
+        test("killed", b"10", b"10"),
+    ]);
+    let options = TestOptions {
+        jobs: 3,
+        ..TestOptions::default()
+    };
+
+    let start = Instant::now();
+    let command = "sh -c 'read t; sleep $t; echo $t'";
+    let results = test_problem(&problem, command, &options).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    let names: Vec<_> = results.iter().map(|r| r.test_name.as_str()).collect();
+    assert_eq!(names, ["slow", "wrong"]);
+    assert!(results[0].status.is_success());
+    assert!(!results[1].status.is_success());
+}
+
+#[test]
+fn runtime_error() {
+    use std::borrow::Cow;
+
+    let test = Test {
+        test_name: "1".into(),
+        input: Cow::Borrowed(b""),
+        output: Cow::Borrowed(b""),
+        time_limit: None,
+    };
+    let limits = Limits::with_wall_time(Duration::from_secs(10));
+    let judge = Judge::Compare(&CompareMode::Lines);
+
+    let result = run_test(&test, "sh -c 'kill -SEGV $$'", &limits, &judge).unwrap();
+    assert!(matches!(
+        result.status,
+        ResultStatus::RuntimeError {
+            signal: libc::SIGSEGV
+        }
+    ));
+
+    let result = run_test(&test, "sh -c 'exit 3'", &limits, &judge).unwrap();
+    assert!(matches!(
+        result.status,
+        ResultStatus::IncorrectExitCode { exit_code: 3 }
+    ));
+}
+
+#[test]
+fn interactive_verdict() {
+    use macro_types::Program;
+    use std::borrow::Cow;
+
+    let interactor = Judge::Interactor(
+        PreparedProgram::new(&Program {
+            file_name: "interactor".into(),
+            source: Cow::Borrowed(
+                b"#!/bin/sh\ncat \"$1\"\nread x\n[ \"$x\" = \"$(cat \"$2\")\" ] || { echo \"got $x\" >&2; exit 1; }\n",
+            ),
+        })
+        .unwrap(),
+    );
+    let test = Test {
+        test_name: "1".into(),
+        input: Cow::Borrowed(b"21\n"),
+        output: Cow::Borrowed(b"42\n"),
+        time_limit: None,
+    };
+    let limits = Limits::with_wall_time(Duration::from_secs(10));
+
+    let result = run_test(
+        &test,
+        "sh -c 'read n; echo $((n * 2))'",
+        &limits,
+        &interactor,
+    )
+    .unwrap();
+    assert!(matches!(result.status, ResultStatus::Success));
+    assert_eq!(result.transcript, "< 21\n> 42\n");
+
+    let result = run_test(&test, "sh -c 'read n; echo $n'", &limits, &interactor).unwrap();
+    assert!(
+        matches!(result.status, ResultStatus::CheckerRejected { message } if message == "got 21")
+    );
+}
+
+#[test]
+fn huge_allocation() {
+    use std::borrow::Cow;
+
+    // Set when the test runs itself as the solution, see below
+    const ALLOCATE_ENV: &str = "CHINT_TEST_ALLOCATE";
+    if std::env::var_os(ALLOCATE_ENV).is_some() {
+        std::hint::black_box(Vec::<u8>::with_capacity(10 << 30));
+        return;
     }
-    Ok(true)
+
+    let test = Test {
+        test_name: "1".into(),
+        input: Cow::Borrowed(b""),
+        output: Cow::Borrowed(b""),
+        time_limit: None,
+    };
+    let limits = Limits {
+        memory: Some(64 * 1024 * 1024),
+        ..Limits::with_wall_time(Duration::from_secs(10))
+    };
+    let judge = Judge::Compare(&CompareMode::Lines);
+    let exe = std::env::current_exe().unwrap();
+    let command = shlex::join([
+        "env",
+        &format!("{ALLOCATE_ENV}=1"),
+        exe.to_str().unwrap(),
+        "--exact",
+        "test_runner::huge_allocation",
+    ]);
+
+    let result = run_test(&test, &command, &limits, &judge).unwrap();
+    assert!(
+        matches!(result.status, ResultStatus::MemoryLimitExceeded),
+        "{}",
+        result.stderr
+    );
+
+    // Not every crash is caused by the limit
+    let result = run_test(&test, "sh -c 'kill -SEGV $$'", &limits, &judge).unwrap();
+    assert!(matches!(
+        result.status,
+        ResultStatus::RuntimeError {
+            signal: libc::SIGSEGV
+        }
+    ));
 }
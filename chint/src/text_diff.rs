@@ -1,14 +1,23 @@
 use anyhow::Context;
 use difference::{Changeset, Difference};
-use std::io::Write;
+
+use crate::compare::Mismatch;
 
 // Screenshot:
 // https://raw.githubusercontent.com/johannhof/difference.rs/master/assets/github-style.png
 
-pub fn print_diff(text1: &str, text2: &str) -> anyhow::Result<()> {
-    let Changeset { diffs, .. } = Changeset::new(text1, text2, "\n");
-
+pub fn print_diff(text1: &str, text2: &str, mismatch: &Mismatch) -> anyhow::Result<()> {
     let mut t = term::stdout().context("Terminal missing?")?;
+    write_diff(&mut *t, text1, text2, mismatch)
+}
+
+fn write_diff(
+    t: &mut (impl term::Terminal + ?Sized),
+    text1: &str,
+    text2: &str,
+    mismatch: &Mismatch,
+) -> anyhow::Result<()> {
+    let Changeset { diffs, .. } = Changeset::new(text1, text2, "\n");
 
     for i in 0..diffs.len() {
         match diffs[i] {
@@ -17,8 +26,8 @@ pub fn print_diff(text1: &str, text2: &str) -> anyhow::Result<()> {
                 writeln!(t, " {}", x)?;
             }
             Difference::Add(ref x) => {
-                match diffs[i - 1] {
-                    Difference::Rem(ref y) => {
+                match i.checked_sub(1).map(|i| &diffs[i]) {
+                    Some(Difference::Rem(ref y)) => {
                         t.fg(term::color::GREEN)?;
                         write!(t, "+")?;
                         let Changeset { diffs, .. } = Changeset::new(y, x, " ");
@@ -54,6 +63,55 @@ pub fn print_diff(text1: &str, text2: &str) -> anyhow::Result<()> {
         }
     }
     t.reset()?;
+
+    writeln!(t, "First mismatch at line {}:", mismatch.line)?;
+    t.fg(term::color::GREEN)?;
+    write!(t, "  expected: ")?;
+    write_token(t, mismatch.expected.as_deref())?;
+    t.fg(term::color::RED)?;
+    write!(t, "  received: ")?;
+    write_token(t, mismatch.received.as_deref())?;
+    t.reset()?;
     t.flush()?;
     Ok(())
 }
+
+fn write_token(t: &mut (impl term::Terminal + ?Sized), token: Option<&str>) -> anyhow::Result<()> {
+    match token {
+        Some(token) => {
+            t.attr(term::Attr::Bold)?;
+            writeln!(t, "{:?}", token)?;
+        }
+        None => writeln!(t, "<end of output>")?,
+    }
+    Ok(())
+}
+
+#[test]
+fn empty_output() {
+    use std::collections::HashMap;
+    use term::terminfo::{TermInfo, TerminfoTerminal};
+    use term::Terminal;
+
+    // Colors that print nothing
+    let info = TermInfo {
+        names: vec![],
+        bools: HashMap::new(),
+        numbers: HashMap::from([("colors", 16)]),
+        strings: ["setaf", "setab", "sgr0", "bold"]
+            .into_iter()
+            .map(|cap| (cap, vec![]))
+            .collect(),
+    };
+    let mut t = TerminfoTerminal::new_with_terminfo(vec![], info);
+    let mismatch = Mismatch {
+        line: 1,
+        expected: Some("42".to_string()),
+        received: None,
+    };
+
+    write_diff(&mut t, "", "42", &mismatch).unwrap();
+    let text = String::from_utf8(t.into_inner()).unwrap();
+    assert!(text.contains("+42\n"), "{text}");
+    assert!(text.contains("received: <end of output>"), "{text}");
+}
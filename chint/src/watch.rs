@@ -0,0 +1,98 @@
+//! `chint test --watch`, running the tests again whenever the solution changes
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Context;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::language;
+
+/// Changes closer together than this are handled as one, editors often write a file in steps
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Calls `run` in a new thread, and again whenever `file` or another source file of its
+/// language in the same directory changes. The flag given to `run` is set when it should stop
+/// because of a new change.
+pub fn watch(
+    file: &Path,
+    run: impl Fn(Arc<AtomicBool>) + Send + Clone + 'static,
+) -> anyhow::Result<()> {
+    let file = file
+        .canonicalize()
+        .with_context(|| format!("Unable to find {}", file.display()))?;
+    let dir = file
+        .parent()
+        .context("Solution has no directory")?
+        .to_path_buf();
+    let extensions = match language::by_file(&file)? {
+        Some(language) => language.extensions,
+        None => vec![],
+    };
+    // Editors often save by replacing the file, so the directory is watched instead of the file
+    let is_input = |path: &Path| {
+        path == file
+            || path.parent() == Some(&dir)
+                && path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| extensions.iter().any(|x| x == e))
+    };
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Unable to watch {}", dir.display()))?;
+
+    loop {
+        // Clear the screen and move the cursor to the top
+        print!("\x1b[2J\x1b[H");
+        println!("=== Watching {}, press Ctrl-C to stop", file.display());
+        io::stdout().flush()?;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let run = run.clone();
+        let running = {
+            let cancel = cancel.clone();
+            thread::spawn(move || run(cancel))
+        };
+
+        wait_for_change(&events, is_input)?;
+        cancel.store(true, Ordering::Relaxed);
+        // A panic in a run shouldn't stop watching, it was already printed
+        let _ = running.join();
+    }
+}
+
+/// Returns once a file matching `is_input` changed and there were no more changes for
+/// [`DEBOUNCE`]
+fn wait_for_change(
+    events: &Receiver<notify::Result<Event>>,
+    is_input: impl Fn(&Path) -> bool,
+) -> anyhow::Result<()> {
+    let is_change = |event: &Event| {
+        !matches!(event.kind, EventKind::Access(_)) && event.paths.iter().any(|p| is_input(p))
+    };
+    loop {
+        let event = events.recv().context("Watcher stopped")??;
+        if is_change(&event) {
+            break;
+        }
+    }
+    loop {
+        match events.recv_timeout(DEBOUNCE) {
+            Ok(event) => {
+                event?;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => return Ok(()),
+            Err(mpsc::RecvTimeoutError::Disconnected) => anyhow::bail!("Watcher stopped"),
+        }
+    }
+}
@@ -0,0 +1,8 @@
+// {{title}}
+#include <stdio.h>
+
+int main(void) {
+    // TODO: read the input with scanf and print the answer with printf
+
+    return 0;
+}
@@ -0,0 +1,12 @@
+// {{title}}
+#include <bits/stdc++.h>
+using namespace std;
+
+int main() {
+    ios::sync_with_stdio(false);
+    cin.tie(nullptr);
+
+    // TODO: read the input from cin and print the answer to cout
+
+    return 0;
+}
@@ -0,0 +1,16 @@
+// {{title}}
+package main
+
+import (
+	"bufio"
+	"os"
+)
+
+func main() {
+	reader := bufio.NewReader(os.Stdin)
+	writer := bufio.NewWriter(os.Stdout)
+	defer writer.Flush()
+
+	// TODO: read the input with fmt.Fscan(reader, ...) and print the answer with fmt.Fprintln(writer, ...)
+	_ = reader
+}
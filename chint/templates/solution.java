@@ -0,0 +1,14 @@
+// {{title}}
+import java.io.*;
+import java.util.*;
+
+class Main {
+    public static void main(String[] args) throws IOException {
+        BufferedReader reader = new BufferedReader(new InputStreamReader(System.in));
+        PrintWriter writer = new PrintWriter(new BufferedWriter(new OutputStreamWriter(System.out)));
+
+        // TODO: read the input from reader and print the answer to writer
+
+        writer.flush();
+    }
+}
@@ -0,0 +1,4 @@
+// {{title}}
+const tokens = require("fs").readFileSync(0, "utf8").split(/\s+/).filter(Boolean);
+
+// TODO: solve the problem and print the answer with console.log
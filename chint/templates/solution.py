@@ -0,0 +1,10 @@
+# {{title}}
+import sys
+
+
+def main():
+    tokens = sys.stdin.read().split()
+    # TODO: solve the problem and print the answer
+
+
+main()
@@ -0,0 +1,4 @@
+# {{title}}
+tokens = STDIN.read.split
+
+# TODO: solve the problem and print the answer with puts
@@ -0,0 +1,13 @@
+// {{title}}
+use std::io::{self, BufWriter, Read, Write};
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    let mut tokens = input.split_whitespace();
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    // TODO: read the input from `tokens` and write the answer to `out`
+    let _ = tokens.next();
+    out.flush().unwrap();
+}
@@ -1,13 +1,102 @@
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+mod loader;
+
+pub use loader::{load_problems, LoadError};
+
 pub type FileContent = [u8];
 
+#[derive(Clone, Debug)]
 pub struct Test<'a> {
-    pub test_name: &'a str,
-    pub input: &'a FileContent,
-    pub output: &'a FileContent,
+    pub test_name: Cow<'a, str>,
+    pub input: Cow<'a, FileContent>,
+    pub output: Cow<'a, FileContent>,
+    /// Overrides the problem's time limit
+    pub time_limit: Option<Duration>,
+}
+
+/// A helper program shipped with a problem, e.g. a checker or an interactor
+#[derive(Clone, Debug)]
+pub struct Program<'a> {
+    /// File name in the problem directory, the extension decides how it is run
+    pub file_name: Cow<'a, str>,
+    pub source: Cow<'a, FileContent>,
+}
+
+/// How the output of a solution is compared with the expected output
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum CompareMode {
+    /// Byte by byte
+    Exact,
+    /// Line by line, ignoring trailing whitespace and trailing empty lines
+    #[default]
+    Lines,
+    /// Whitespace separated tokens
+    Tokens,
+    /// Whitespace separated tokens, numbers are equal if they are within either epsilon
+    Float {
+        #[serde(default = "default_epsilon")]
+        absolute: f64,
+        #[serde(default = "default_epsilon")]
+        relative: f64,
+    },
+    /// Whitespace separated tokens, ignoring ASCII case
+    CaseInsensitive,
+}
+
+fn default_epsilon() -> f64 {
+    1e-6
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        })
+    }
 }
 
+#[derive(Clone, Debug)]
 pub struct Problem<'a> {
-    pub title: &'a str,
-    pub description: &'a str,
-    pub tests: &'a [Test<'a>],
+    /// Position in the problem set, starting from 1
+    pub id: u64,
+    /// Stable identifier, e.g. `odd-even-check`
+    pub slug: Cow<'a, str>,
+    /// Without the leading `#`
+    pub title: Cow<'a, str>,
+    pub description: Cow<'a, str>,
+    pub difficulty: Option<Difficulty>,
+    pub tags: Cow<'a, [Cow<'a, str>]>,
+    pub author: Option<Cow<'a, str>>,
+    /// Where the problem is from, usually a URL
+    pub source: Option<Cow<'a, str>>,
+    pub tests: Cow<'a, [Test<'a>]>,
+    /// Decides if an output is correct, instead of comparing it with the expected output
+    pub checker: Option<Program<'a>>,
+    /// Talks with the solution and decides if it is correct, for interactive problems
+    pub interactor: Option<Program<'a>>,
+    /// A correct solution, the expected outputs can be made with it
+    pub reference: Option<Program<'a>>,
+    /// Prints a random input, run with a seed as its only argument
+    pub generator: Option<Program<'a>>,
+    /// Ignored if there is a checker or an interactor
+    pub compare: CompareMode,
+    pub time_limit: Option<Duration>,
+    /// In megabytes
+    pub memory_limit: Option<u64>,
 }
@@ -0,0 +1,463 @@
+//! Reads a directory of problems into owned [`Problem`]s.
+//!
+//! This is used both by `macros::include_dir!` at compile time and by chint at
+//! runtime, so the two always accept exactly the same layout:
+//!
+//! ```text
+//! <root>/1-hello-world/description.md   first line is the default title, rest is the description
+//! <root>/1-hello-world/<name>.in        input of test <name>
+//! <root>/1-hello-world/<name>.out       expected output of test <name>
+//! <root>/1-hello-world/problem.toml     optional metadata, see below
+//! <root>/1-hello-world/checker[.ext]    optional, see below
+//! <root>/1-hello-world/interactor[.ext] optional, see below
+//! <root>/1-hello-world/reference[.ext]  optional reference solution
+//! <root>/1-hello-world/generator[.ext]  optional, see below
+//! <root>/2/...
+//! ```
+//!
+//! Hidden files and directories, e.g. `.git`, are ignored.
+//!
+//! Problem directories are named `<id>` or `<id>-<slug>`. Ids must go from 1 without
+//! gaps. The slug is a stable name for the problem, if it isn't in the directory name
+//! or in `problem.toml` it is made from the title (`problem-<id>` if the title has no
+//! letters).
+//!
+//! `problem.toml` can describe the problem, set limits and how outputs are
+//! compared (see [`CompareMode`]). Every key is optional:
+//!
+//! ```toml
+//! slug = "odd-even-check"
+//! title = "Odd Even Check"   # instead of the first line of description.md
+//! difficulty = "easy"        # or "medium", "hard"
+//! tags = ["math", "parity"]
+//! author = "Jane Doe"
+//! source = "https://example.com/problems/42"
+//! time_limit = 1.5   # seconds
+//! memory_limit = 256 # megabytes
+//!
+//! [tests.3]          # overrides for test 3
+//! time_limit = 5
+//!
+//! [compare]
+//! mode = "float"  # or "exact", "lines" (default), "tokens", "case-insensitive"
+//! absolute = 1e-6 # only for "float"
+//! relative = 1e-6 # only for "float"
+//! ```
+//!
+//! A checker is run as `checker <input> <output> <expected output>` (same as
+//! testlib checkers). It exits with 0 to accept the output, 1 or 2 to reject it,
+//! and writes the reason to stderr.
+//!
+//! Interactive problems have an interactor instead of a checker. It is run as
+//! `interactor <input> <expected output>`, what it prints is the input of the
+//! solution and the output of the solution is its input. It exits like a checker
+//! once the solution is done; the expected output is whatever the interactor
+//! needs, e.g. the hidden answer.
+//!
+//! The reference solution and the generator are for maintaining the problem. The
+//! expected outputs can be made with the reference solution (`chint problem regen`),
+//! and `chint stress` compares solutions with it on inputs printed by
+//! `generator <seed>`.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{CompareMode, Difficulty, Problem, Program, Test};
+
+/// Contents of `problem.toml`
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Metadata {
+    slug: Option<String>,
+    title: Option<String>,
+    difficulty: Option<Difficulty>,
+    #[serde(default)]
+    tags: Vec<String>,
+    author: Option<String>,
+    source: Option<String>,
+    #[serde(default)]
+    compare: CompareMode,
+    /// In seconds
+    time_limit: Option<f64>,
+    memory_limit: Option<u64>,
+    #[serde(default)]
+    tests: HashMap<String, TestMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestMetadata {
+    /// In seconds
+    time_limit: Option<f64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LoadError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl LoadError {
+    fn new(path: &Path, reason: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\": {}", self.path.display(), self.reason)
+    }
+}
+
+/// Load all the problems in `root`.
+///
+/// `track` is called with every directory and file that is read, so that the
+/// compile-time caller can ask the compiler to watch them.
+pub fn load_problems(
+    root: &Path,
+    mut track: impl FnMut(&Path),
+) -> Result<Vec<Problem<'static>>, LoadError> {
+    let mut id_to_dir = BTreeMap::new();
+    for child in read_dir(root, &mut track)? {
+        if !child.is_dir() {
+            continue;
+        }
+        let name = file_name(&child)?;
+        let (id, slug) = match name.split_once('-') {
+            Some((id, slug)) => (id, Some(slug.to_string())),
+            None => (name, None),
+        };
+        let id = id.parse::<u64>().map_err(|_| {
+            LoadError::new(
+                &child,
+                "Problem directories must be named <id> or <id>-<slug>",
+            )
+        })?;
+        if let Some(other) = id_to_dir.insert(id, (child.clone(), slug)) {
+            let reason = format!("Same id as {}", other.0.display());
+            return Err(LoadError::new(&child, reason));
+        }
+    }
+
+    if let Some(i) = (1..)
+        .zip(id_to_dir.keys())
+        .find_map(|(i, &id)| (id != i).then_some(i))
+    {
+        return Err(LoadError::new(root, format!("Problem {i} is missing")));
+    }
+
+    let mut problems: Vec<Problem<'static>> = vec![];
+    for (id, (dir, slug)) in id_to_dir {
+        let problem = load_problem(&dir, id, slug, &mut track)?;
+        if let Some(other) = problems.iter().find(|p| p.slug == problem.slug) {
+            let reason = format!("Same slug {:?} as problem {}", problem.slug, other.id);
+            return Err(LoadError::new(&dir, reason));
+        }
+        problems.push(problem);
+    }
+    Ok(problems)
+}
+
+fn load_problem(
+    dir: &Path,
+    id: u64,
+    slug: Option<String>,
+    track: &mut impl FnMut(&Path),
+) -> Result<Problem<'static>, LoadError> {
+    let mut problem_description = None;
+    let mut tests = vec![];
+    let mut current_test: Option<(String, Vec<u8>)> = None;
+    let mut checker = None;
+    let mut interactor = None;
+    let mut reference = None;
+    let mut generator = None;
+    let mut metadata = Metadata::default();
+
+    // `read_dir` sorts by name, so `<name>.in` always comes right before `<name>.out`
+    for child in read_dir(dir, track)? {
+        let name = file_name(&child)?;
+
+        match name {
+            "description.md" => {
+                let content = String::from_utf8(read_file(&child, track)?)
+                    .map_err(|_| LoadError::new(&child, "description.md is not valid UTF-8"))?;
+                problem_description = Some(content);
+            }
+            "problem.toml" => {
+                let content = String::from_utf8(read_file(&child, track)?)
+                    .map_err(|_| LoadError::new(&child, "problem.toml is not valid UTF-8"))?;
+                metadata = toml::from_str(&content)
+                    .map_err(|e| LoadError::new(&child, format!("Invalid problem.toml: {e}")))?;
+            }
+            x if x.ends_with(".out") => {
+                let test_name = x.strip_suffix(".out").unwrap();
+                let (name, input) = match current_test.take() {
+                    Some((name, input)) if name == test_name => (name, input),
+                    _ => return Err(LoadError::new(&child, "Out file without input file")),
+                };
+                tests.push(Test {
+                    test_name: Cow::Owned(name),
+                    input: Cow::Owned(input),
+                    output: Cow::Owned(read_file(&child, track)?),
+                    time_limit: None,
+                });
+            }
+            x if x.ends_with(".in") => {
+                if let Some((name, _)) = current_test {
+                    return Err(LoadError::new(
+                        dir,
+                        format!("Possibly missing output file for {name:?}"),
+                    ));
+                }
+                let test_name = x.strip_suffix(".in").unwrap();
+                current_test = Some((test_name.to_string(), read_file(&child, track)?));
+            }
+            x if is_program(x, "checker") => checker = Some(read_program(&child, track)?),
+            x if is_program(x, "interactor") => interactor = Some(read_program(&child, track)?),
+            x if is_program(x, "reference") => reference = Some(read_program(&child, track)?),
+            x if is_program(x, "generator") => generator = Some(read_program(&child, track)?),
+            _ => return Err(LoadError::new(&child, "Unexpected file")),
+        }
+    }
+    if let Some((name, _)) = current_test {
+        return Err(LoadError::new(
+            dir,
+            format!("Possibly missing output file for {name:?}"),
+        ));
+    }
+
+    if checker.is_some() && interactor.is_some() {
+        return Err(LoadError::new(dir, "Both a checker and an interactor"));
+    }
+
+    let metadata_path = dir.join("problem.toml");
+    for (name, test_metadata) in metadata.tests {
+        let test = tests
+            .iter_mut()
+            .find(|test| test.test_name == name)
+            .ok_or_else(|| LoadError::new(&metadata_path, format!("No test named {name:?}")))?;
+        test.time_limit = to_duration(test_metadata.time_limit, &metadata_path)?;
+    }
+
+    let problem_desc =
+        problem_description.ok_or_else(|| LoadError::new(dir, "description.md missing"))?;
+    let (title, description) = problem_desc
+        .split_once('\n')
+        .ok_or_else(|| LoadError::new(dir, "Invalid format in description.md"))?;
+
+    let title = metadata
+        .title
+        .unwrap_or_else(|| title.trim_start_matches('#').trim().to_string());
+    let slug = match metadata.slug.or(slug) {
+        Some(slug) if is_valid_slug(&slug) => slug,
+        Some(slug) => {
+            let reason = format!(
+                "Invalid slug {slug:?}, use lowercase letters, digits and '-', and at least one letter"
+            );
+            return Err(LoadError::new(dir, reason));
+        }
+        None => default_slug(&title, id),
+    };
+
+    Ok(Problem {
+        id,
+        slug: Cow::Owned(slug),
+        title: Cow::Owned(title),
+        description: Cow::Owned(description.to_string()),
+        difficulty: metadata.difficulty,
+        tags: metadata.tags.into_iter().map(Cow::Owned).collect(),
+        author: metadata.author.map(Cow::Owned),
+        source: metadata.source.map(Cow::Owned),
+        tests: Cow::Owned(tests),
+        checker,
+        interactor,
+        reference,
+        generator,
+        compare: metadata.compare,
+        time_limit: to_duration(metadata.time_limit, &metadata_path)?,
+        memory_limit: metadata.memory_limit,
+    })
+}
+
+/// `name` is `<stem>` or `<stem>.<ext>`
+fn is_program(name: &str, stem: &str) -> bool {
+    name.strip_prefix(stem)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+fn read_program(path: &Path, track: &mut impl FnMut(&Path)) -> Result<Program<'static>, LoadError> {
+    Ok(Program {
+        file_name: Cow::Owned(file_name(path)?.to_string()),
+        source: Cow::Owned(read_file(path, track)?),
+    })
+}
+
+/// Slugs can't be only digits, so that they can't be confused with ids
+fn is_valid_slug(slug: &str) -> bool {
+    slug.bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        && slug.bytes().any(|b| b.is_ascii_lowercase())
+}
+
+/// The slug made from the title, or `problem-<id>` if the title has no letters
+fn default_slug(title: &str, id: u64) -> String {
+    let slug = slugify(title);
+    match is_valid_slug(&slug) {
+        true => slug,
+        false => format!("problem-{id}"),
+    }
+}
+
+/// e.g. "Odd/Even Check" -> "odd-even-check"
+fn slugify(title: &str) -> String {
+    let lowercase = title.to_ascii_lowercase();
+    let words: Vec<_> = lowercase
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.join("-")
+}
+
+fn to_duration(seconds: Option<f64>, path: &Path) -> Result<Option<Duration>, LoadError> {
+    seconds
+        .map(|seconds| {
+            Duration::try_from_secs_f64(seconds)
+                .ok()
+                .filter(|duration| !duration.is_zero())
+                .ok_or_else(|| LoadError::new(path, format!("Invalid time limit {seconds}")))
+        })
+        .transpose()
+}
+
+fn file_name(path: &Path) -> Result<&str, LoadError> {
+    path.file_name()
+        .and_then(|x| x.to_str())
+        .ok_or_else(|| LoadError::new(path, "File name is not valid UTF-8"))
+}
+
+/// Sorted paths of the entries in `dir`, without hidden ones like `.git`
+fn read_dir(dir: &Path, track: &mut impl FnMut(&Path)) -> Result<Vec<PathBuf>, LoadError> {
+    if !dir.is_dir() {
+        return Err(LoadError::new(dir, "Not a directory"));
+    }
+
+    track(dir);
+
+    let unreadable = |e| LoadError::new(dir, format!("Unable to read the entries: {e}"));
+    let mut paths = Vec::new();
+    for entry in dir.read_dir().map_err(unreadable)? {
+        let entry = entry.map_err(unreadable)?;
+        if !entry.file_name().as_encoded_bytes().starts_with(b".") {
+            paths.push(entry.path());
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn read_file(path: &Path, track: &mut impl FnMut(&Path)) -> Result<Vec<u8>, LoadError> {
+    track(path);
+    std::fs::read(path).map_err(|e| LoadError::new(path, format!("Unable to read: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_bundled_problems() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../chint/problems");
+        let mut tracked = vec![];
+
+        let problems = load_problems(&root, |p| tracked.push(p.to_path_buf())).unwrap();
+
+        assert_eq!(problems.len(), 3);
+        assert_eq!(problems[0].title, "Hello World");
+        assert_eq!(problems[0].slug, "hello-world");
+        assert_eq!(problems[1].id, 2);
+        assert_eq!(problems[1].slug, "odd-even-check");
+        assert_eq!(problems[1].difficulty, Some(Difficulty::Easy));
+        assert_eq!(*problems[1].tags, ["math"]);
+        assert_eq!(problems[0].tests.len(), 1);
+        assert_eq!(problems[1].tests.len(), 4);
+        assert_eq!(problems[1].tests[0].test_name, "1");
+        assert_eq!(&*problems[1].tests[0].output, b"false\n");
+        assert!(problems[1].checker.is_none());
+        assert!(problems[1].interactor.is_none());
+        let interactor = problems[2].interactor.as_ref().unwrap();
+        assert_eq!(interactor.file_name, "interactor.py");
+        assert!(problems[0].reference.is_none());
+        assert_eq!(
+            problems[1].reference.as_ref().unwrap().file_name,
+            "reference.py"
+        );
+        assert_eq!(
+            problems[1].generator.as_ref().unwrap().file_name,
+            "generator.py"
+        );
+        assert_eq!(problems[1].compare, CompareMode::Lines);
+        assert_eq!(problems[0].time_limit, None);
+        assert_eq!(problems[1].time_limit, Some(Duration::from_secs(2)));
+        assert!(tracked.contains(&root.join("2-odd-even-check/description.md")));
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slugify("# Odd/Even  Check!"), "odd-even-check");
+        assert!(is_valid_slug("two-sum-2"));
+        assert!(!is_valid_slug("42"));
+        assert!(!is_valid_slug("Two Sum"));
+        assert_eq!(default_slug("Two Sum", 3), "two-sum");
+        assert_eq!(default_slug("2048", 3), "problem-3");
+        assert_eq!(default_slug("???", 4), "problem-4");
+    }
+
+    #[test]
+    fn hidden_entries() {
+        let root = std::env::temp_dir().join(format!("chint-hidden-{}", std::process::id()));
+        let problem = root.join("1-hello");
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(&problem).unwrap();
+        std::fs::write(problem.join("description.md"), "# Hello\n").unwrap();
+        std::fs::write(problem.join(".DS_Store"), "").unwrap();
+
+        let problems = load_problems(&root, |_| {});
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(problems.unwrap()[0].slug, "hello");
+    }
+
+    #[test]
+    fn time_limits() {
+        let path = Path::new("problem.toml");
+        assert_eq!(
+            to_duration(Some(1.5), path),
+            Ok(Some(Duration::from_millis(1500)))
+        );
+        assert_eq!(to_duration(None, path), Ok(None));
+        assert!(to_duration(Some(0.0), path).is_err());
+        assert!(to_duration(Some(-1.0), path).is_err());
+    }
+
+    #[test]
+    fn missing_root() {
+        let err = load_problems(Path::new("/does/not/exist"), |_| {}).unwrap_err();
+
+        assert_eq!(err.reason, "Not a directory");
+    }
+}
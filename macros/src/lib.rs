@@ -4,13 +4,14 @@
 //! You probably don't want to use this crate directly.
 #![cfg_attr(feature = "nightly", feature(track_path, proc_macro_tracked_env))]
 
+use macro_types::{CompareMode, Difficulty, Problem, Program, Test};
 use proc_macro::{TokenStream, TokenTree};
 use quote::quote;
 use std::{
-    collections::HashMap,
     error::Error,
     fmt::{self, Display, Formatter},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 /// Embed the contents of a directory in your crate.
@@ -25,7 +26,7 @@ pub fn include_dir(input: TokenStream) -> TokenStream {
 
     let path = resolve_path(&path, get_env).unwrap();
 
-    expand_dir(&path, &path).into()
+    expand_dir(&path).into()
 }
 
 fn unwrap_string_literal(lit: &proc_macro::Literal) -> String {
@@ -40,136 +41,124 @@ fn unwrap_string_literal(lit: &proc_macro::Literal) -> String {
     repr
 }
 
-fn expand_dir(root: &Path, path: &Path) -> proc_macro2::TokenStream {
-    let children = read_dir(path).unwrap_or_else(|e| {
-        panic!(
-            "Unable to read the entries in \"{}\": {}",
-            path.display(),
-            e
-        )
-    });
-    let mut number_to_dir = HashMap::new();
-    for child in children {
-        let name = child.file_name().unwrap().to_str().unwrap();
-        if let Ok(x) = name.parse::<u64>() {
-            number_to_dir.insert(x, child);
-        }
-    }
-    let mut problems = vec![];
-
-    for i in 1..u64::MAX {
-        let dir = match number_to_dir.get(&i) {
-            Some(x) => x,
-            None => break,
-        };
-        let mut childs = read_dir(dir).unwrap_or_else(|e| {
-            panic!(
-                "Unable to read the entries in \"{}\": {}",
-                path.display(),
-                e
-            )
-        });
-        let mut problem_description = None;
-        let mut tests = vec![];
-        let mut current_test_name: Option<String> = None;
-        let mut current_test_input: Option<Vec<u8>> = None;
-        childs.sort_by(|a, b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
-        for child in childs {
-            let name = child.file_name().unwrap().to_str().unwrap();
-
-            match name {
-                "description.md" => {
-                    let content = String::from_utf8(read_file(&child)).unwrap();
-                    problem_description = Some(content);
-                }
-                x if x.ends_with(".out") => {
-                    let problem_name = x.strip_suffix(".out").unwrap();
-                    assert_eq!(
-                        problem_name,
-                        &current_test_name
-                            .clone()
-                            .expect("Out file without input file")
-                    );
-                    let name = current_test_name.expect("Outfile without input file");
-                    let input = current_test_input.unwrap();
-                    let output = read_file(&child);
-                    tests.push(quote! {macro_types::Test {
-                        test_name: #name,
-                        input: &[#(#input), *],
-                        output: &[#(#output), *],
-                    }});
-                    current_test_name = None;
-                    current_test_input = None;
-                }
-                x if x.ends_with(".in") => {
-                    let problem_name = x.strip_suffix(".in").unwrap();
-                    assert!(
-                        current_test_input.is_none() && current_test_name.is_none(),
-                        "Possibly missing output file for {current_test_name:?}"
-                    );
-                    current_test_name = Some(problem_name.to_string());
-                    current_test_input = Some(read_file(&child));
-                }
-                _ => panic!("Unexpected file {name} in {child:?}"),
-            }
+fn expand_dir(path: &Path) -> proc_macro2::TokenStream {
+    let problems = match macro_types::load_problems(path, track_path) {
+        Ok(problems) => problems,
+        Err(e) => {
+            let message = format!("Unable to load problems: {e}");
+            return quote! { compile_error!(#message) };
         }
-        let problem_desc = problem_description.expect("description.md missing");
-        let (title, description) = problem_desc
-            .split_once('\n')
-            .expect("Invalid format in description.md");
-
-        problems.push(quote! {
-            macro_types::Problem {
-                title: #title,
-                description: #description,
-                tests: &[#(#tests), *],
-            }
-        });
+    };
+    let problems = problems.iter().map(quote_problem);
 
-        let _description_path = dir.join("description.md");
-        let _description_path = dir.join("description.md");
+    quote! {
+        &[ #(#problems),*]
     }
+}
 
-    let _path = normalize_path(root, path);
+fn quote_problem(problem: &Problem) -> proc_macro2::TokenStream {
+    let id = problem.id;
+    let slug = problem.slug.as_ref();
+    let title = problem.title.as_ref();
+    let description = problem.description.as_ref();
+    let difficulty = quote_option(problem.difficulty.map(quote_difficulty));
+    let tags = problem.tags.iter().map(|tag| tag.as_ref());
+    let author = quote_option(problem.author.as_deref().map(quote_str));
+    let source = quote_option(problem.source.as_deref().map(quote_str));
+    let tests = problem.tests.iter().map(quote_test);
+    let checker = quote_option(problem.checker.as_ref().map(quote_program));
+    let interactor = quote_option(problem.interactor.as_ref().map(quote_program));
+    let reference = quote_option(problem.reference.as_ref().map(quote_program));
+    let generator = quote_option(problem.generator.as_ref().map(quote_program));
+    let compare = quote_compare_mode(&problem.compare);
+    let time_limit = quote_option(problem.time_limit.map(quote_duration));
+    let memory_limit = quote_option(problem.memory_limit.map(|limit| quote! { #limit }));
 
     quote! {
-        &[ #(#problems),*]
+        macro_types::Problem {
+            id: #id,
+            slug: ::std::borrow::Cow::Borrowed(#slug),
+            title: ::std::borrow::Cow::Borrowed(#title),
+            description: ::std::borrow::Cow::Borrowed(#description),
+            difficulty: #difficulty,
+            tags: ::std::borrow::Cow::Borrowed(&[#(::std::borrow::Cow::Borrowed(#tags)), *]),
+            author: #author,
+            source: #source,
+            tests: ::std::borrow::Cow::Borrowed(&[#(#tests), *]),
+            checker: #checker,
+            interactor: #interactor,
+            reference: #reference,
+            generator: #generator,
+            compare: #compare,
+            time_limit: #time_limit,
+            memory_limit: #memory_limit,
+        }
     }
 }
 
-/// Make sure that paths use the same separator regardless of whether the host
-/// machine is Windows or Linux.
-fn normalize_path(root: &Path, path: &Path) -> String {
-    let stripped = path
-        .strip_prefix(root)
-        .expect("Should only ever be called using paths inside the root path");
-    let as_string = stripped.to_string_lossy();
+fn quote_difficulty(difficulty: Difficulty) -> proc_macro2::TokenStream {
+    match difficulty {
+        Difficulty::Easy => quote! { macro_types::Difficulty::Easy },
+        Difficulty::Medium => quote! { macro_types::Difficulty::Medium },
+        Difficulty::Hard => quote! { macro_types::Difficulty::Hard },
+    }
+}
 
-    as_string.replace('\\', "/")
+fn quote_str(s: &str) -> proc_macro2::TokenStream {
+    quote! { ::std::borrow::Cow::Borrowed(#s) }
 }
 
-fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-    if !dir.is_dir() {
-        panic!("\"{}\" is not a directory", dir.display());
+fn quote_compare_mode(mode: &CompareMode) -> proc_macro2::TokenStream {
+    match mode {
+        CompareMode::Exact => quote! { macro_types::CompareMode::Exact },
+        CompareMode::Lines => quote! { macro_types::CompareMode::Lines },
+        CompareMode::Tokens => quote! { macro_types::CompareMode::Tokens },
+        CompareMode::Float { absolute, relative } => quote! {
+            macro_types::CompareMode::Float { absolute: #absolute, relative: #relative }
+        },
+        CompareMode::CaseInsensitive => quote! { macro_types::CompareMode::CaseInsensitive },
     }
+}
 
-    track_path(dir);
-
-    let mut paths = Vec::new();
+fn quote_program(program: &Program) -> proc_macro2::TokenStream {
+    let file_name = program.file_name.as_ref();
+    let source = program.source.iter();
 
-    for entry in dir.read_dir()? {
-        let entry = entry?;
-        paths.push(entry.path());
+    quote! {
+        macro_types::Program {
+            file_name: ::std::borrow::Cow::Borrowed(#file_name),
+            source: ::std::borrow::Cow::Borrowed(&[#(#source), *]),
+        }
     }
+}
 
-    paths.sort();
+fn quote_duration(duration: Duration) -> proc_macro2::TokenStream {
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos();
+    quote! { ::std::time::Duration::new(#secs, #nanos) }
+}
 
-    Ok(paths)
+fn quote_option(value: Option<proc_macro2::TokenStream>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote! { ::std::option::Option::Some(#value) },
+        None => quote! { ::std::option::Option::None },
+    }
 }
 
-fn read_file(path: &Path) -> Vec<u8> {
-    track_path(path);
-    std::fs::read(path).unwrap_or_else(|e| panic!("Unable to read \"{}\": {}", path.display(), e))
+fn quote_test(test: &Test) -> proc_macro2::TokenStream {
+    let name = test.test_name.as_ref();
+    let input = test.input.iter();
+    let output = test.output.iter();
+    let time_limit = quote_option(test.time_limit.map(quote_duration));
+
+    quote! {
+        macro_types::Test {
+            test_name: ::std::borrow::Cow::Borrowed(#name),
+            input: ::std::borrow::Cow::Borrowed(&[#(#input), *]),
+            output: ::std::borrow::Cow::Borrowed(&[#(#output), *]),
+            time_limit: #time_limit,
+        }
+    }
 }
 
 fn resolve_path(